@@ -0,0 +1,177 @@
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, Mul};
+
+// Unsigned integer of arbitrary size, for results that outgrow u128
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BigUint {
+    limbs: Vec<u64>, // base LIMB, least significant first, no leading zero limbs
+}
+
+const LIMB: u64 = 1_000_000_000_000_000_000;
+const LIMB_DIGITS: usize = 18;
+
+impl BigUint {
+    // Decimal digits, most significant first
+    pub fn from_digits(digits: &[u64]) -> Self {
+        let mut limbs: Vec<u64> = digits
+            .rchunks(LIMB_DIGITS)
+            .map(|chunk| chunk.iter().fold(0, |acc, &d| acc * 10 + d))
+            .collect();
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        BigUint { limbs }
+    }
+}
+
+impl From<u128> for BigUint {
+    fn from(mut n: u128) -> Self {
+        let mut limbs = Vec::new();
+        while n > 0 {
+            limbs.push((n % LIMB as u128) as u64);
+            n /= LIMB as u128;
+        }
+        BigUint { limbs }
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: BigUint) -> BigUint {
+        let (mut long, short) = if self.limbs.len() >= other.limbs.len() {
+            (self.limbs, other.limbs)
+        } else {
+            (other.limbs, self.limbs)
+        };
+        let mut carry = 0;
+        for (i, limb) in long.iter_mut().enumerate() {
+            let sum = *limb + short.get(i).unwrap_or(&0) + carry;
+            *limb = sum % LIMB;
+            carry = sum / LIMB;
+        }
+        if carry > 0 {
+            long.push(carry);
+        }
+        BigUint { limbs: long }
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: BigUint) -> BigUint {
+        if self.limbs.is_empty() || other.limbs.is_empty() {
+            return BigUint::default();
+        }
+        let mut limbs = vec![0; self.limbs.len() + other.limbs.len()];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            let mut carry = 0;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                // fits: (LIMB - 1)^2 + 2 * (LIMB - 1) < u128::MAX
+                let product = a as u128 * b as u128 + limbs[i + j] as u128 + carry;
+                limbs[i + j] = (product % LIMB as u128) as u64;
+                carry = product / LIMB as u128;
+            }
+            limbs[i + other.limbs.len()] = carry as u64;
+        }
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        BigUint { limbs }
+    }
+}
+
+impl Sum for BigUint {
+    fn sum<I: Iterator<Item = BigUint>>(iter: I) -> BigUint {
+        iter.fold(BigUint::default(), |acc, n| acc + n)
+    }
+}
+
+impl PartialEq<u128> for BigUint {
+    fn eq(&self, other: &u128) -> bool {
+        let mut rest = *other;
+        let limbs_equal = self.limbs.iter().all(|&limb| {
+            let equal = limb as u128 == rest % LIMB as u128;
+            rest /= LIMB as u128;
+            equal
+        });
+        limbs_equal && rest == 0
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.limbs.split_last() {
+            None => write!(f, "0"),
+            Some((most_significant, rest)) => {
+                write!(f, "{most_significant}")?;
+                rest.iter()
+                    .rev()
+                    .try_for_each(|limb| write!(f, "{limb:018}"))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_from_u128() {
+    assert_eq!(BigUint::from(0).to_string(), "0");
+    assert_eq!(BigUint::from(42).to_string(), "42");
+    assert_eq!(
+        BigUint::from(u128::MAX).to_string(),
+        "340282366920938463463374607431768211455"
+    );
+    assert_eq!(
+        BigUint::from(1_000_000_000_000_000_000).to_string(),
+        "1000000000000000000"
+    );
+}
+
+#[test]
+fn test_from_digits() {
+    assert_eq!(BigUint::from_digits(&[]), 0);
+    assert_eq!(BigUint::from_digits(&[0, 0, 4, 2]), 42);
+    let digits: Vec<u64> = "12345678901234567890123456789012345678901234567890"
+        .chars()
+        .map(|c| c.to_digit(10).unwrap().into())
+        .collect();
+    assert_eq!(
+        BigUint::from_digits(&digits).to_string(),
+        "12345678901234567890123456789012345678901234567890"
+    );
+}
+
+#[test]
+fn test_add() {
+    assert_eq!(BigUint::from(999) + BigUint::from(1), 1000);
+    assert_eq!(BigUint::from(0) + BigUint::from(0), 0);
+    let sum = BigUint::from(u128::MAX) + BigUint::from(u128::MAX);
+    assert_eq!(sum.to_string(), "680564733841876926926749214863536422910");
+}
+
+#[test]
+fn test_mul() {
+    assert_eq!(BigUint::from(12) * BigUint::from(34), 408);
+    assert_eq!(BigUint::from(0) * BigUint::from(u128::MAX), 0);
+    let square = BigUint::from(u128::MAX) * BigUint::from(u128::MAX);
+    assert_eq!(
+        square.to_string(),
+        "115792089237316195423570985008687907852589419931798687112530834793049593217025"
+    );
+    let limb = BigUint::from(1_000_000_000_000_000_000);
+    assert_eq!(
+        (limb.clone() * limb).to_string(),
+        "1000000000000000000000000000000000000"
+    );
+}
+
+#[test]
+fn test_sum() {
+    let sum: BigUint = [u128::MAX, 1, u128::MAX]
+        .into_iter()
+        .map(BigUint::from)
+        .sum();
+    assert_eq!(sum.to_string(), "680564733841876926926749214863536422911");
+}
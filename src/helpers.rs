@@ -1,31 +1,400 @@
-pub fn transpose<T>(v: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    let n_cols = v[0].len();
-    let mut lines: Vec<_> = v.into_iter().map(|line| line.into_iter()).collect();
-
-    (0..n_cols)
-        .map(|_| {
-            lines
-                .iter_mut()
-                .map(|line| line.next().unwrap())
-                .collect::<Vec<T>>()
+use std::fmt;
+use std::iter;
+use std::ops::{Index, IndexMut};
+
+// None for ragged rows
+pub fn transpose<T: Clone>(rows: Vec<Vec<T>>) -> Option<Vec<Vec<T>>> {
+    transform_rows(rows, Grid::transposed)
+}
+
+// Applies one of the grid transforms below to rows, None for ragged rows
+pub fn transform_rows<T: Clone>(
+    rows: Vec<Vec<T>>,
+    transform: impl FnOnce(&Grid<T>) -> Grid<T>,
+) -> Option<Vec<Vec<T>>> {
+    Some(transform(&Grid::try_from_rows(rows)?).into_rows())
+}
+
+pub type Pos = (usize, usize); // (y, x)
+
+pub const NEIGHBORS_4: [(isize, isize); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+pub const NEIGHBORS_8: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<T>, // row by row
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, fill: T) -> Grid<T> {
+        Grid {
+            width,
+            height,
+            cells: vec![fill; width * height],
+        }
+    }
+
+    fn from_fn(width: usize, height: usize, cell: impl Fn(Pos) -> T) -> Grid<T> {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (y, x)))
+            .map(cell)
+            .collect();
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    pub fn transposed(&self) -> Grid<T> {
+        Grid::from_fn(self.height, self.width, |(y, x)| self[(x, y)].clone())
+    }
+}
+
+#[allow(dead_code)] // used by the p12_grid port, which main does not build
+impl<T: Clone> Grid<T> {
+    // Counterclockwise
+    pub fn rotated90(&self) -> Grid<T> {
+        let last_x = self.width.wrapping_sub(1);
+        Grid::from_fn(self.height, self.width, |(y, x)| {
+            self[(x, last_x - y)].clone()
+        })
+    }
+
+    pub fn rotated180(&self) -> Grid<T> {
+        self.flipped_horizontal().flipped_vertical()
+    }
+
+    pub fn rotated270(&self) -> Grid<T> {
+        let last_y = self.height.wrapping_sub(1);
+        Grid::from_fn(self.height, self.width, |(y, x)| {
+            self[(last_y - x, y)].clone()
+        })
+    }
+
+    // Mirrored left to right
+    pub fn flipped_horizontal(&self) -> Grid<T> {
+        let last_x = self.width.wrapping_sub(1);
+        Grid::from_fn(self.width, self.height, |(y, x)| {
+            self[(y, last_x - x)].clone()
+        })
+    }
+
+    // Mirrored top to bottom
+    pub fn flipped_vertical(&self) -> Grid<T> {
+        let last_y = self.height.wrapping_sub(1);
+        Grid::from_fn(self.width, self.height, |(y, x)| {
+            self[(last_y - y, x)].clone()
         })
-        .collect()
+    }
+
+    // The 4 rotations, then the 4 rotations of the horizontal flip.
+    // Symmetric grids show up more than once.
+    pub fn symmetries(&self) -> [Grid<T>; 8] {
+        let flipped = self.flipped_horizontal();
+        [
+            self.clone(),
+            self.rotated90(),
+            self.rotated180(),
+            self.rotated270(),
+            flipped.clone(),
+            flipped.rotated90(),
+            flipped.rotated180(),
+            flipped.rotated270(),
+        ]
+    }
+}
+
+impl<T> Grid<T> {
+    // Short lines are padded as if they ended in spaces
+    pub fn parse(input: &str, cell: impl Fn(char) -> T) -> Grid<T> {
+        let width = input.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+        let height = input.lines().count();
+        let cells = input
+            .lines()
+            .flat_map(|l| l.chars().chain(iter::repeat(' ')).take(width))
+            .map(cell)
+            .collect();
+        Grid {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn from_rows(rows: Vec<Vec<T>>) -> Grid<T> {
+        Grid::try_from_rows(rows).expect("ragged rows")
+    }
+
+    // None for ragged rows
+    pub fn try_from_rows(rows: Vec<Vec<T>>) -> Option<Grid<T>> {
+        let height = rows.len();
+        let width = rows.first().map_or(0, Vec::len);
+        if rows.iter().any(|row| row.len() != width) {
+            return None;
+        }
+        Some(Grid {
+            width,
+            height,
+            cells: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn into_rows(self) -> Vec<Vec<T>> {
+        let mut cells = self.cells.into_iter();
+        (0..self.height)
+            .map(|_| cells.by_ref().take(self.width).collect())
+            .collect()
+    }
+
+    pub fn get(&self, (y, x): Pos) -> Option<&T> {
+        if y < self.height && x < self.width {
+            Some(&self.cells[y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = Pos> + use<T> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (y, x)))
+    }
+
+    #[cfg(test)]
+    pub fn neighbors4(&self, pos: Pos) -> impl Iterator<Item = Pos> + use<'_, T> {
+        self.neighbors(pos, &NEIGHBORS_4)
+    }
+
+    #[cfg(test)]
+    pub fn neighbors8(&self, pos: Pos) -> impl Iterator<Item = Pos> + use<'_, T> {
+        self.neighbors(pos, &NEIGHBORS_8)
+    }
+
+    #[cfg(test)]
+    fn neighbors(
+        &self,
+        pos: Pos,
+        deltas: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = Pos> + use<'_, T> {
+        deltas
+            .iter()
+            .filter_map(move |&delta| self.offset(pos, delta))
+    }
+
+    // None when the step leaves the grid
+    pub fn offset(&self, (y, x): Pos, (dy, dx): (isize, isize)) -> Option<Pos> {
+        let y = y.checked_add_signed(dy).filter(|&y| y < self.height)?;
+        let x = x.checked_add_signed(dx).filter(|&x| x < self.width)?;
+        Some((y, x))
+    }
+
+    // Wraps around the edges, as on a torus
+    pub fn offset_wrapping(&self, (y, x): Pos, (dy, dx): (isize, isize)) -> Pos {
+        let wrap =
+            |v: usize, d: isize, len: usize| (v as isize + d).rem_euclid(len as isize) as usize;
+        (wrap(y, dy, self.height), wrap(x, dx, self.width))
+    }
+
+    #[cfg(test)]
+    pub fn map<U>(&self, f: impl Fn(&T) -> U) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+}
+
+impl<T> Index<Pos> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, pos: Pos) -> &T {
+        self.get(pos).expect("position outside of grid")
+    }
+}
+
+impl<T> IndexMut<Pos> for Grid<T> {
+    fn index_mut(&mut self, (y, x): Pos) -> &mut T {
+        assert!(
+            y < self.height && x < self.width,
+            "position outside of grid"
+        );
+        &mut self.cells[y * self.width + x]
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Grid<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in self.rows() {
+            row.iter().try_for_each(|cell| write!(f, "{cell}"))?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
 }
 
+// Seeded linear congruential generator for reproducible test data
 #[cfg(test)]
-pub fn rot90<T: Clone>(v: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    let rows = v.len();
-    let cols = v[0].len();
-    assert_eq!(rows, cols);
+pub struct Lcg(u64);
 
-    (0..rows)
-        .map(|i| (0..cols).map(|j| v[j][rows-i-1].clone()).collect())
-        .collect()
+#[cfg(test)]
+impl Lcg {
+    pub fn new(seed: u64) -> Lcg {
+        Lcg(seed)
+    }
+
+    // Next value in 0..n
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) % n
+    }
 }
 
 #[test]
 fn test_rot90() {
     let v = vec![vec![1, 2], vec![3, 4]];
     let rotated = vec![vec![2, 4], vec![1, 3]];
-    assert_eq!(rot90(v), rotated);
+    assert_eq!(transform_rows(v, Grid::rotated90), Some(rotated));
+}
+
+#[test]
+fn test_transpose() {
+    let v = vec![vec![1, 2, 3], vec![4, 5, 6]];
+    let transposed = vec![vec![1, 4], vec![2, 5], vec![3, 6]];
+    assert_eq!(transpose(v), Some(transposed));
+    assert_eq!(transpose(Vec::<Vec<u8>>::new()), Some(vec![]));
+    assert_eq!(transpose(vec![vec![1, 2], vec![3]]), None);
+}
+
+#[test]
+fn test_grid_transforms() {
+    // 1 2 3
+    // 4 5 6
+    let grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]);
+    let rows = |g: Grid<i32>| g.into_rows();
+    assert_eq!(
+        rows(grid.rotated90()),
+        vec![vec![3, 6], vec![2, 5], vec![1, 4]]
+    );
+    assert_eq!(rows(grid.rotated180()), vec![vec![6, 5, 4], vec![3, 2, 1]]);
+    assert_eq!(
+        rows(grid.rotated270()),
+        vec![vec![4, 1], vec![5, 2], vec![6, 3]]
+    );
+    assert_eq!(
+        rows(grid.flipped_horizontal()),
+        vec![vec![3, 2, 1], vec![6, 5, 4]]
+    );
+    assert_eq!(
+        rows(grid.flipped_vertical()),
+        vec![vec![4, 5, 6], vec![1, 2, 3]]
+    );
+    assert_eq!(grid.rotated90().rotated90(), grid.rotated180());
+    assert_eq!(grid.rotated90().rotated270(), grid);
+    assert_eq!(grid.transposed(), grid.flipped_horizontal().rotated90());
+}
+
+#[test]
+fn test_grid_transforms_empty() {
+    let empty: Grid<u8> = Grid::new(0, 0, 0);
+    assert_eq!(empty.rotated90(), empty);
+    let no_columns: Grid<u8> = Grid::new(0, 2, 0);
+    assert_eq!(no_columns.rotated270(), Grid::new(2, 0, 0));
+    assert_eq!(no_columns.flipped_horizontal(), no_columns);
+}
+
+#[test]
+fn test_grid_symmetries() {
+    let grid = Grid::from_rows(vec![vec![1, 2, 3], vec![4, 5, 6]]);
+    let symmetries = grid.symmetries();
+    let distinct: std::collections::HashSet<_> = symmetries.iter().collect();
+    assert_eq!(distinct.len(), 8);
+    assert!(symmetries.contains(&grid.transposed()));
+    assert!(symmetries.contains(&grid.flipped_vertical()));
+    assert_eq!(symmetries[1], grid.rotated90());
+    assert_eq!(symmetries[4], grid.flipped_horizontal());
+    assert_eq!(symmetries[5], grid.flipped_horizontal().rotated90());
+    let square = Grid::new(2, 2, 0);
+    assert!(square.symmetries().iter().all(|s| *s == square));
+}
+
+#[cfg(test)]
+static GRID_EXAMPLE: &str = "..@\n@@.\n";
+
+#[test]
+fn test_grid_parse() {
+    let grid = Grid::parse(GRID_EXAMPLE, |c| c == '@');
+    assert_eq!((grid.width, grid.height), (3, 2));
+    assert!(grid[(0, 2)]);
+    assert!(!grid[(1, 2)]);
+    assert_eq!(grid.get((2, 0)), None);
+    assert_eq!(grid.row(1), &[true, true, false]);
+}
+
+#[test]
+fn test_grid_parse_ragged() {
+    let grid = Grid::parse("ab\nc\n", |c| c);
+    assert_eq!(grid.width, 2);
+    assert_eq!(grid.row(1), &['c', ' ']);
+}
+
+#[test]
+fn test_grid_rows() {
+    let rows = vec![vec![1, 2, 3], vec![4, 5, 6]];
+    let grid = Grid::from_rows(rows.clone());
+    assert_eq!((grid.width, grid.height), (3, 2));
+    assert_eq!(grid[(1, 0)], 4);
+    assert_eq!(grid.into_rows(), rows);
+}
+
+#[test]
+fn test_grid_neighbors() {
+    let grid = Grid::new(3, 2, 0);
+    let corner: Vec<_> = grid.neighbors8((0, 0)).collect();
+    assert_eq!(corner, vec![(0, 1), (1, 0), (1, 1)]);
+    let middle: Vec<_> = grid.neighbors4((1, 1)).collect();
+    assert_eq!(middle, vec![(0, 1), (1, 0), (1, 2)]);
+    assert_eq!(grid.neighbors8((1, 1)).count(), 5);
+}
+
+#[test]
+fn test_grid_offset() {
+    let grid = Grid::new(3, 2, 0);
+    assert_eq!(grid.offset((0, 0), (1, 2)), Some((1, 2)));
+    assert_eq!(grid.offset((0, 0), (-1, 0)), None);
+    assert_eq!(grid.offset((1, 2), (0, 1)), None);
+    assert_eq!(grid.offset_wrapping((0, 0), (-1, -1)), (1, 2));
+    assert_eq!(grid.offset_wrapping((1, 2), (1, 1)), (0, 0));
+    assert_eq!(grid.offset_wrapping((0, 1), (-5, 7)), (1, 2));
+}
+
+#[test]
+fn test_grid_display() {
+    let mut grid = Grid::parse(GRID_EXAMPLE, |c| c == '@');
+    grid[(0, 0)] = true;
+    let rendered = grid.map(|&roll| if roll { '@' } else { '.' }).to_string();
+    assert_eq!(rendered, "@.@\n@@.\n");
 }
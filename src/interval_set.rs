@@ -0,0 +1,286 @@
+#[cfg(test)]
+use crate::helpers::Lcg;
+use std::ops::RangeInclusive;
+
+// Integer types whose values can be enumerated one after the other
+pub trait Discrete: Copy + Ord {
+    fn succ(self) -> Option<Self>;
+    #[cfg(test)]
+    fn pred(self) -> Option<Self>;
+    fn distance(self, other: Self) -> u128; // other - self, for self <= other
+}
+
+impl Discrete for u64 {
+    fn succ(self) -> Option<u64> {
+        self.checked_add(1)
+    }
+
+    #[cfg(test)]
+    fn pred(self) -> Option<u64> {
+        self.checked_sub(1)
+    }
+
+    fn distance(self, other: u64) -> u128 {
+        (other - self) as u128
+    }
+}
+
+// Set of values stored as sorted, disjoint and non-adjacent inclusive ranges
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSet<T> {
+    ranges: Vec<(T, T)>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        IntervalSet { ranges: Vec::new() }
+    }
+}
+
+impl<T: Discrete> IntervalSet<T> {
+    pub fn total_len(&self) -> u128 {
+        self.ranges.iter().map(|&(s, e)| s.distance(e) + 1).sum()
+    }
+
+    // Number of the values in the set, values must be sorted ascending
+    pub fn count_sorted(&self, values: &[T]) -> usize {
+        let mut ranges = self.ranges.iter().peekable();
+        values
+            .iter()
+            .filter(|&&value| {
+                while ranges.next_if(|&&(_, end)| end < value).is_some() {}
+                ranges.peek().is_some_and(|&&(start, _)| start <= value)
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+impl<T: Discrete> IntervalSet<T> {
+    pub fn insert(&mut self, range: RangeInclusive<T>) {
+        let (mut start, mut end) = range.into_inner();
+        if start > end {
+            return;
+        }
+        // ranges overlapping or touching [start, end] form the run first..last
+        let first = self
+            .ranges
+            .partition_point(|&(_, e)| e.succ().is_some_and(|e| e < start));
+        let last = self
+            .ranges
+            .partition_point(|&(s, _)| s.pred().is_none_or(|s| s <= end));
+        if first < last {
+            start = start.min(self.ranges[first].0);
+            end = end.max(self.ranges[last - 1].1);
+        }
+        self.ranges.splice(first..last, [(start, end)]);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = RangeInclusive<T>> + '_ {
+        self.ranges.iter().map(|&(s, e)| s..=e)
+    }
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let i = self.ranges.partition_point(|&(_, end)| end < value);
+        self.ranges.get(i).is_some_and(|&(start, _)| start <= value)
+    }
+
+    pub fn remove(&mut self, range: RangeInclusive<T>) {
+        let (start, end) = range.into_inner();
+        if start > end {
+            return;
+        }
+        let first = self.ranges.partition_point(|&(_, e)| e < start);
+        let last = self.ranges.partition_point(|&(s, _)| s <= end);
+        if first == last {
+            return;
+        }
+        let (head, _) = self.ranges[first];
+        let (_, tail) = self.ranges[last - 1];
+        let mut kept = Vec::new();
+        if head < start {
+            kept.push((head, start.pred().unwrap()));
+        }
+        if tail > end {
+            kept.push((end.succ().unwrap(), tail));
+        }
+        self.ranges.splice(first..last, kept);
+    }
+
+    pub fn union(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut union = self.clone();
+        other.iter().for_each(|range| union.insert(range));
+        union
+    }
+
+    pub fn intersection(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut ranges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ranges.len() && j < other.ranges.len() {
+            let (a_start, a_end) = self.ranges[i];
+            let (b_start, b_end) = other.ranges[j];
+            let (start, end) = (a_start.max(b_start), a_end.min(b_end));
+            if start <= end {
+                ranges.push((start, end));
+            }
+            if a_end < b_end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { ranges }
+    }
+
+    pub fn difference(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut difference = self.clone();
+        other.iter().for_each(|range| difference.remove(range));
+        difference
+    }
+
+    // Values within bounds that are not in the set
+    pub fn complement(&self, bounds: RangeInclusive<T>) -> IntervalSet<T> {
+        let mut complement: IntervalSet<T> = [bounds].into_iter().collect();
+        self.iter().for_each(|range| complement.remove(range));
+        complement
+    }
+}
+
+impl<T: Discrete> FromIterator<RangeInclusive<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = RangeInclusive<T>>>(iter: I) -> Self {
+        // sorting first avoids shifting the vector on every insert
+        let mut sorted: Vec<(T, T)> = iter
+            .into_iter()
+            .map(RangeInclusive::into_inner)
+            .filter(|(start, end)| start <= end)
+            .collect();
+        sorted.sort_unstable();
+        let mut ranges: Vec<(T, T)> = Vec::with_capacity(sorted.len());
+        for (start, end) in sorted {
+            match ranges.last_mut() {
+                Some((_, last_end)) if last_end.succ().is_none_or(|e| e >= start) => {
+                    *last_end = end.max(*last_end)
+                }
+                _ => ranges.push((start, end)),
+            }
+        }
+        IntervalSet { ranges }
+    }
+}
+
+#[cfg(test)]
+fn ranges(set: &IntervalSet<u64>) -> Vec<(u64, u64)> {
+    set.iter().map(|r| r.into_inner()).collect()
+}
+
+#[test]
+fn test_insert() {
+    let mut set = IntervalSet::new();
+    set.insert(10..=20);
+    set.insert(30..=40);
+    set.insert(0..=2);
+    assert_eq!(ranges(&set), vec![(0, 2), (10, 20), (30, 40)]);
+    set.insert(21..=29);
+    assert_eq!(ranges(&set), vec![(0, 2), (10, 40)]);
+    set.insert(4..=8);
+    assert_eq!(ranges(&set), vec![(0, 2), (4, 8), (10, 40)]);
+    set.insert(1..=50);
+    assert_eq!(ranges(&set), vec![(0, 50)]);
+    let (start, end) = (5, 4);
+    set.insert(start..=end);
+    assert_eq!(ranges(&set), vec![(0, 50)]);
+}
+
+#[test]
+fn test_insert_extremes() {
+    let set: IntervalSet<u64> = [u64::MAX - 1..=u64::MAX, 0..=0, 1..=u64::MAX - 2]
+        .into_iter()
+        .collect();
+    assert_eq!(ranges(&set), vec![(0, u64::MAX)]);
+    assert_eq!(set.total_len(), 1 << 64);
+}
+
+#[test]
+fn test_remove() {
+    let mut set: IntervalSet<u64> = [0..=10, 20..=30].into_iter().collect();
+    set.remove(5..=24);
+    assert_eq!(ranges(&set), vec![(0, 4), (25, 30)]);
+    set.remove(12..=18);
+    assert_eq!(ranges(&set), vec![(0, 4), (25, 30)]);
+    set.remove(26..=26);
+    assert_eq!(ranges(&set), vec![(0, 4), (25, 25), (27, 30)]);
+    set.remove(0..=u64::MAX);
+    assert!(set.is_empty());
+}
+
+#[test]
+fn test_set_operations() {
+    let a: IntervalSet<u64> = [0..=10, 20..=30].into_iter().collect();
+    let b: IntervalSet<u64> = [5..=25, 40..=50].into_iter().collect();
+    assert_eq!(ranges(&a.union(&b)), vec![(0, 30), (40, 50)]);
+    assert_eq!(ranges(&a.intersection(&b)), vec![(5, 10), (20, 25)]);
+    assert_eq!(ranges(&a.difference(&b)), vec![(0, 4), (26, 30)]);
+    assert_eq!(ranges(&b.difference(&a)), vec![(11, 19), (40, 50)]);
+    assert_eq!(ranges(&a.complement(5..=35)), vec![(11, 19), (31, 35)]);
+    assert_eq!(a.complement(0..=u64::MAX).total_len(), (1 << 64) - 22);
+}
+
+#[test]
+fn test_contains() {
+    let set: IntervalSet<u64> = [3..=5, 10..=14, u64::MAX..=u64::MAX].into_iter().collect();
+    let members: Vec<u64> = [0, 3, 5, 6, 9, 10, 14, 15, u64::MAX - 1, u64::MAX]
+        .into_iter()
+        .filter(|&v| set.contains(v))
+        .collect();
+    assert_eq!(members, vec![3, 5, 10, 14, u64::MAX]);
+    assert!(!IntervalSet::new().contains(0));
+}
+
+#[test]
+fn test_count_sorted() {
+    let set: IntervalSet<u64> = [3..=5, 10..=14].into_iter().collect();
+    assert_eq!(set.count_sorted(&[0, 3, 4, 4, 6, 12, 14, 20]), 5);
+    assert_eq!(set.count_sorted(&[]), 0);
+    assert_eq!(IntervalSet::new().count_sorted(&[1, 2]), 0);
+}
+
+#[test]
+fn test_total_len() {
+    let set: IntervalSet<u64> = [3..=5, 10..=14, 16..=20, 12..=18].into_iter().collect();
+    assert_eq!(set.total_len(), 14);
+    assert_eq!(IntervalSet::<u64>::new().total_len(), 0);
+}
+
+#[test]
+fn test_matches_brute_force() {
+    let mut rng = Lcg::new(7);
+    let mut next = |n| rng.below(n);
+    let mut set = IntervalSet::new();
+    let mut members = [false; 100];
+    for _ in 0..500 {
+        let start = next(100);
+        let end = (start + next(10)).min(99);
+        let insert = next(3) > 0;
+        if insert {
+            set.insert(start..=end);
+        } else {
+            set.remove(start..=end);
+        }
+        members[start as usize..=end as usize].fill(insert);
+        let expected: Vec<u64> = (0..100).filter(|&i| members[i as usize]).collect();
+        let actual: Vec<u64> = set.iter().flatten().collect();
+        assert_eq!(actual, expected);
+        assert!((0..100).all(|i| set.contains(i) == members[i as usize]));
+        let all: Vec<u64> = (0..100).collect();
+        assert_eq!(set.count_sorted(&all), expected.len());
+        assert!(set.ranges.windows(2).all(|w| w[0].1 + 1 < w[1].0));
+    }
+}
@@ -5,6 +5,8 @@ mod p02;
 mod p03;
 mod p04;
 mod p05;
+#[cfg(test)]
+mod p05_store;
 mod p06;
 mod p07;
 mod p08;
@@ -15,6 +17,8 @@ mod p12;
 //mod p12_grid;
 //mod p12_collision_map;
 mod helpers;
+mod big_uint;
+mod interval_set;
 
 fn main() {
     type SolveFunc = fn(bool) -> String;
@@ -1,14 +1,44 @@
 use std::collections::HashMap;
+use std::fs::File;
+#[cfg(test)]
+use std::io::{self, Write};
+use std::io::{BufRead, BufReader};
 
 pub fn solve(part2: bool) -> String {
-    let rotations = load_rotations();
+    let file = File::open("input_01.txt").expect("could not read file");
+    let (exact_zeros, all_zeros) = count_zeros(BufReader::new(file), 50);
     if part2 {
-        solve_2(rotations).to_string()
+        all_zeros.to_string()
     } else {
-        solve_1(rotations).to_string()
+        exact_zeros.to_string()
+    }
+}
+
+// Single pass over the input, returns (part 1, part 2)
+fn count_zeros<R: BufRead>(reader: R, position: i128) -> (u128, u128) {
+    let (mut exact_zeros, mut all_zeros) = (0, 0);
+    for (_, step) in steps(reader, position) {
+        let ends_on_zero = step.ends_on_zero() as u128;
+        exact_zeros += ends_on_zero;
+        all_zeros += step.crossings + ends_on_zero;
     }
+    (exact_zeros, all_zeros)
 }
 
+// (line number, step) for each rotation read from reader, one line at a time
+fn steps<R: BufRead>(reader: R, mut position: i128) -> impl Iterator<Item = (usize, Step)> {
+    (1..)
+        .zip(reader.lines())
+        .map(|(number, line)| (number, line.expect("could not read line")))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(move |(number, line)| {
+            let step = rotate(position, parse_rotation(line.trim()));
+            position = step.end;
+            (number, step)
+        })
+}
+
+#[cfg(test)]
 fn solve_1(rotations: Vec<i128>) -> u128 {
     apply_rotations(rotations, 50)
         .iter()
@@ -18,12 +48,15 @@ fn solve_1(rotations: Vec<i128>) -> u128 {
         .unwrap()
 }
 
+#[cfg(test)]
 fn solve_2(rotations: Vec<i128>) -> u128 {
-    let exact_zeros = solve_1(rotations.clone());
-    let skipped_zeros: u128 = apply_rotations_skipped_zeros(rotations, 50).iter().sum();
-    exact_zeros + skipped_zeros
+    trace_rotations(&rotations, 50)
+        .iter()
+        .map(|step| step.crossings + step.ends_on_zero() as u128)
+        .sum()
 }
 
+#[cfg(test)]
 fn load_rotations() -> Vec<i128> {
     std::fs::read_to_string("input_01.txt")
         .expect("could not read file")
@@ -44,6 +77,7 @@ fn parse_rotation(line: &str) -> i128 {
     sign * abs
 }
 
+#[cfg(test)]
 fn apply_rotations(rotations: Vec<i128>, mut init: i128) -> Vec<i128> {
     rotations
         .iter()
@@ -55,31 +89,107 @@ fn apply_rotations(rotations: Vec<i128>, mut init: i128) -> Vec<i128> {
         .collect()
 }
 
-fn apply_rotations_skipped_zeros(rotations: Vec<i128>, mut init: i128) -> Vec<u128> {
+#[cfg(test)]
+fn apply_rotations_skipped_zeros(rotations: Vec<i128>, init: i128) -> Vec<u128> {
+    trace_rotations(&rotations, init)
+        .iter()
+        .map(|step| step.crossings)
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Step {
+    start: i128,
+    end: i128,
+    crossings: u128, // times 0 was passed, not counting `end`
+}
+
+impl Step {
+    fn ends_on_zero(&self) -> bool {
+        self.end == 0
+    }
+}
+
+#[cfg(test)]
+fn trace_rotations(rotations: &[i128], mut init: i128) -> Vec<Step> {
     rotations
         .iter()
-        .map(|&rot_raw| {
-            let full_turns = rot_raw.abs().div_euclid(100);
-            let rot = rot_raw - 100 * full_turns * rot_raw.signum();
-
-            let prev = init;
-            let next_raw = init + rot;
-            let next = next_raw.rem_euclid(100);
-
-            let sign_change = next_raw > 100 || next_raw < 0;
-            let skips = if sign_change && prev != 0 && next != 0 {
-                1
-            } else {
-                0
-            };
-            let zero_to_zero = prev == 0 && next == 0;
-            let fix = if zero_to_zero { -1 } else { 0 }; // full turns from zero to zero are counted twice in part 1 already
-            init = next;
-            skips + (full_turns + fix).unsigned_abs()
+        .map(|&rot| {
+            let step = rotate(init, rot);
+            init = step.end;
+            step
         })
         .collect()
 }
 
+fn rotate(start: i128, rot: i128) -> Step {
+    let end = (start + rot).rem_euclid(100);
+    // zero_hits includes `end`, except for a rotation by 0 which moves nowhere
+    let crossings = if rot == 0 {
+        0
+    } else {
+        zero_hits(start, rot) - (end == 0) as u128
+    };
+    Step {
+        start,
+        end,
+        crossings,
+    }
+}
+
+#[cfg(test)]
+struct ZeroReport {
+    hits: Vec<(usize, Step)>, // (line number, step) for each rotation touching 0
+    longest_quiet: (usize, usize), // (first line number, rotations) of the longest run not touching 0
+}
+
+#[cfg(test)]
+fn zero_report<R: BufRead>(reader: R, position: i128) -> ZeroReport {
+    let mut hits = Vec::new();
+    let mut longest_quiet = (1, 0);
+    // blank lines are skipped, so the run is counted apart from the line numbers
+    let (mut quiet_since, mut quiet) = (1, 0);
+
+    for (line, step) in steps(reader, position) {
+        if step.crossings > 0 || step.ends_on_zero() {
+            hits.push((line, step));
+            quiet = 0;
+        } else {
+            if quiet == 0 {
+                quiet_since = line;
+            }
+            quiet += 1;
+            if quiet > longest_quiet.1 {
+                longest_quiet = (quiet_since, quiet);
+            }
+        }
+    }
+    ZeroReport {
+        hits,
+        longest_quiet,
+    }
+}
+
+fn zero_hits(start: i128, rot: i128) -> u128 {
+    // multiples of 100 in (start, start + rot] resp. [start + rot, start)
+    let hits = if rot >= 0 {
+        (start + rot).div_euclid(100) - start.div_euclid(100)
+    } else {
+        (start - 1).div_euclid(100) - (start + rot - 1).div_euclid(100)
+    };
+    hits.unsigned_abs()
+}
+
+// Writes one CSV row per rotation read from reader, for auditing
+#[cfg(test)]
+fn trace_csv<R: BufRead, W: Write>(reader: R, position: i128, mut out: W) -> io::Result<()> {
+    writeln!(out, "start,end,crossings")?;
+    for (_, step) in steps(reader, position) {
+        writeln!(out, "{},{},{}", step.start, step.end, step.crossings)?;
+    }
+    Ok(())
+}
+
 #[test]
 fn test_load_rotations() {
     let rotations = load_rotations();
@@ -145,6 +255,85 @@ fn test_apply_rotations_skipped_zeros_full_rotations_backwards_from_zero() {
     );
 }
 
+#[test]
+fn test_zero_hits() {
+    assert_eq!(zero_hits(50, 50), 1);
+    assert_eq!(zero_hits(50, -50), 1);
+    assert_eq!(zero_hits(0, 100), 1);
+    assert_eq!(zero_hits(0, -100), 1);
+    assert_eq!(zero_hits(0, 0), 0);
+    assert_eq!(zero_hits(99, -1000), 10);
+}
+
+#[test]
+fn test_trace_rotations() {
+    let trace = trace_rotations(&[-68, -30, 48], 50);
+    let rows: Vec<_> = trace
+        .iter()
+        .map(|s| (s.start, s.end, s.crossings))
+        .collect();
+    assert_eq!(rows, vec![(50, 82, 1), (82, 52, 0), (52, 0, 0)]);
+    assert!(trace[2].ends_on_zero());
+}
+
+#[test]
+fn test_trace_csv() {
+    let mut csv = Vec::new();
+    trace_csv("L68\n\nR1000\n".as_bytes(), 50, &mut csv).unwrap();
+    assert_eq!(csv, b"start,end,crossings\n50,82,1\n82,82,10\n");
+}
+
+#[test]
+fn test_count_zeros() {
+    let input = "L68\nL30\nR48\nL5\nR60\nL55\nL1\nL99\nR14\nL82\n";
+    assert_eq!(count_zeros(input.as_bytes(), 50), (3, 6));
+}
+
+#[test]
+fn test_rotate_by_zero() {
+    let step = rotate(0, 0);
+    assert_eq!((step.start, step.end, step.crossings), (0, 0, 0));
+    assert_eq!(rotate(37, 0).crossings, 0);
+    assert_eq!(count_zeros("L50\nR0\n".as_bytes(), 50), (2, 2));
+}
+
+#[test]
+fn test_count_zeros_matches_solve() {
+    let rotations: Vec<i128> = Vec::from([149, -150, 1000, -1, -99, 0, 250, -1001]);
+    let input: String = rotations
+        .iter()
+        .map(|&r| format!("{}{}\n", if r < 0 { 'L' } else { 'R' }, r.abs()))
+        .collect();
+    let expected = (solve_1(rotations.clone()), solve_2(rotations));
+    assert_eq!(count_zeros(input.as_bytes(), 50), expected);
+}
+
+#[test]
+fn test_zero_report() {
+    let input = "L68\nL30\nR48\nL5\nR60\nL55\nL1\nL99\nR14\nL82\n";
+    let report = zero_report(input.as_bytes(), 50);
+    let lines: Vec<_> = report.hits.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![1, 3, 5, 6, 8, 10]);
+    let (_, step) = &report.hits[4];
+    assert_eq!((step.start, step.end, step.crossings), (99, 0, 0));
+    assert_eq!(report.longest_quiet, (2, 1));
+}
+
+#[test]
+fn test_zero_report_longest_quiet() {
+    let report = zero_report("R1\nR1\nR1\nR47\nR1\nR1\n".as_bytes(), 50);
+    assert_eq!(report.hits.len(), 1);
+    assert_eq!(report.longest_quiet, (1, 3));
+
+    let report = zero_report("R50\nR1\nR1\nR1\n".as_bytes(), 50);
+    assert_eq!(report.longest_quiet, (2, 3));
+
+    let report = zero_report("R1\n\n\n\nR1\nR48\n".as_bytes(), 50);
+    assert_eq!(report.longest_quiet, (1, 2));
+    let report = zero_report("R50\n\nR1\n".as_bytes(), 50);
+    assert_eq!(report.longest_quiet, (3, 1));
+}
+
 #[test]
 fn test_solve_part_1_test() {
     let test_rotations = Vec::from([-68, -30, 48, -5, 60, -55, -1, -99, 14, -82]);
@@ -1,3 +1,6 @@
+use crate::big_uint::BigUint;
+use std::collections::BTreeSet;
+
 pub fn solve(part2: bool) -> String {
     let input = std::fs::read_to_string("input_02.txt").expect("could not read file");
     if part2 {
@@ -7,33 +10,62 @@ pub fn solve(part2: bool) -> String {
     }
 }
 
-fn solve_1(input: &str) -> u64 {
-    parse_ranges(input)
-        .into_iter()
-        .map(invalid_in_range)
-        .map(|v| v.iter().sum::<u64>())
-        .sum()
+fn solve_1(input: &str) -> BigUint {
+    invalid_sums(parse_ranges(input), &PART_1).total()
 }
 
-fn solve_2(input: &str) -> u64 {
-    parse_ranges(input)
-        .into_iter()
-        .map(invalid_in_range_2)
-        .map(|v| v.iter().sum::<u64>())
-        .sum()
+fn solve_2(input: &str) -> BigUint {
+    invalid_sums(parse_ranges(input), &PART_2).total()
 }
 
-#[derive(Debug, Eq, PartialEq)]
+type Id = u128;
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
 struct Range {
-    first: u64,
-    last: u64,
+    first: Id, // inclusive
+    last: Id,  // inclusive
+}
+
+struct InvalidSums {
+    per_range: Vec<(Range, BigUint)>, // merged ranges
+}
+
+impl InvalidSums {
+    fn total(&self) -> BigUint {
+        self.per_range.iter().map(|(_, sum)| sum.clone()).sum()
+    }
+}
+
+fn invalid_sums(ranges: Vec<Range>, rule: &InvalidRule) -> InvalidSums {
+    let per_range: Vec<_> = merge_ranges(ranges)
+        .into_iter()
+        .map(|range| {
+            let sum = rule.invalid_in(range).into_iter().map(BigUint::from).sum();
+            (range, sum)
+        })
+        .collect();
+    InvalidSums { per_range }
+}
+
+fn merge_ranges(mut ranges: Vec<Range>) -> Vec<Range> {
+    ranges.sort_by_key(|r| r.first);
+    let mut merged: Vec<Range> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(prev) if range.first <= prev.last.saturating_add(1) => {
+                prev.last = prev.last.max(range.last)
+            }
+            _ => merged.push(range),
+        }
+    }
+    merged
 }
 
 fn parse_ranges(line: &str) -> Vec<Range> {
     let ranges = line.trim().split(',');
     ranges
         .map(|s| {
-            let ids: Vec<u64> = s.split('-').map(|s| s.parse().unwrap()).collect();
+            let ids: Vec<Id> = s.split('-').map(|s| s.parse().unwrap()).collect();
             Range {
                 first: *ids.first().unwrap(),
                 last: *ids.last().unwrap(),
@@ -42,10 +74,11 @@ fn parse_ranges(line: &str) -> Vec<Range> {
         .collect()
 }
 
-fn invalid(id: u64) -> bool {
+#[cfg(test)]
+fn invalid(id: Id) -> bool {
     let n_digits: u32 = id.to_string().len().try_into().unwrap(); // Speedup with log?
 
-    let middle_digit_value = 10u64.pow(n_digits / 2);
+    let middle_digit_value = Id::pow(10, n_digits / 2);
 
     let first = id / middle_digit_value;
     let last = id % middle_digit_value;
@@ -53,7 +86,8 @@ fn invalid(id: u64) -> bool {
     first == last
 }
 
-fn invalid_part_2(id: u64) -> bool {
+#[cfg(test)]
+fn invalid_part_2(id: Id) -> bool {
     let string = id.to_string();
     let digits = string.as_bytes();
     let n_digits = digits.len();
@@ -73,16 +107,233 @@ fn invalid_part_2(id: u64) -> bool {
     false
 }
 
-fn invalid_in_range(range: Range) -> Vec<u64> {
-    (range.first..=range.last + 1)
-        .filter(|&n| invalid(n))
-        .collect()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)] // the puzzle itself only uses the repetition rules
+enum Pattern {
+    Repeated(u32),        // a block of digits repeated exactly k times
+    RepeatedAtLeast(u32), // a block of digits repeated k or more times
+    Palindrome,
+    UpsideDown, // reads the same when rotated by 180°, e.g. 69 or 818
 }
 
-fn invalid_in_range_2(range: Range) -> Vec<u64> {
-    (range.first..=range.last + 1)
-        .filter(|&n| invalid_part_2(n))
-        .collect()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct InvalidRule {
+    pattern: Pattern,
+    base: Id,
+}
+
+const PART_1: InvalidRule = InvalidRule {
+    pattern: Pattern::Repeated(2),
+    base: 10,
+};
+
+const PART_2: InvalidRule = InvalidRule {
+    pattern: Pattern::RepeatedAtLeast(2),
+    base: 10,
+};
+
+#[cfg(test)]
+impl InvalidRule {
+    // Base 0 has no digits and base 1 never shortens an ID, while fewer than
+    // 2 repetitions match nothing or every ID
+    fn new(pattern: Pattern, base: Id) -> Result<InvalidRule, String> {
+        if base < 2 {
+            return Err(format!("base {base} is not at least 2"));
+        }
+        if let Pattern::Repeated(k) | Pattern::RepeatedAtLeast(k) = pattern
+            && k < 2
+        {
+            return Err(format!("repetition count {k} is not at least 2"));
+        }
+        Ok(InvalidRule { pattern, base })
+    }
+
+    fn matches(&self, id: Id) -> bool {
+        let digits = to_digits(id, self.base);
+        let n_digits = digits.len() as u32;
+        match self.pattern {
+            Pattern::Repeated(_) | Pattern::RepeatedAtLeast(_) => {
+                self.periods(n_digits).into_iter().any(|period| {
+                    let mut chunks = digits.chunks_exact(period as usize);
+                    let first = chunks.next().unwrap();
+                    chunks.all(|c| c == first)
+                })
+            }
+            Pattern::Palindrome => digits.iter().eq(digits.iter().rev()),
+            Pattern::UpsideDown => digits
+                .iter()
+                .zip(digits.iter().rev())
+                .all(|(&d, &e)| upside_down(d) == Some(e)),
+        }
+    }
+}
+
+impl InvalidRule {
+    fn invalid_in(&self, range: Range) -> Vec<Id> {
+        match self.pattern {
+            Pattern::Repeated(_) | Pattern::RepeatedAtLeast(_) => self.repeated_in(range),
+            Pattern::Palindrome => self.palindromes_in(range),
+            Pattern::UpsideDown => self.upside_down_in(range),
+        }
+    }
+
+    fn periods(&self, n_digits: u32) -> Vec<u32> {
+        let repetitions_ok = |reps: u32| match self.pattern {
+            Pattern::Repeated(k) => reps == k,
+            Pattern::RepeatedAtLeast(k) => reps >= k,
+            _ => false,
+        };
+        (1..=n_digits)
+            .filter(|&p| n_digits.is_multiple_of(p) && repetitions_ok(n_digits / p))
+            .collect()
+    }
+
+    fn repeated_in(&self, range: Range) -> Vec<Id> {
+        // Every n-digit ID repeating a p-digit pattern is pattern * 1..01..01 (n/p ones)
+        let base = self.base;
+        let mut found = BTreeSet::new();
+        for n_digits in n_digits(range.first, base)..=n_digits(range.last, base) {
+            for period in self.periods(n_digits) {
+                let Some(multiplier) = repunit(n_digits, period, base) else {
+                    continue;
+                };
+                let smallest_pattern = base.pow(period - 1);
+                let largest_pattern = base.checked_pow(period).map_or(Id::MAX, |p| p - 1);
+                let lo = range.first.div_ceil(multiplier).max(smallest_pattern);
+                let hi = (range.last / multiplier).min(largest_pattern);
+                found.extend((lo..=hi).map(|pattern| pattern * multiplier));
+            }
+        }
+        found.into_iter().collect()
+    }
+
+    fn palindromes_in(&self, range: Range) -> Vec<Id> {
+        // A palindrome is determined by its leading half, and grows with it
+        let base = self.base;
+        let mut found = Vec::new();
+        for n_digits in n_digits(range.first, base)..=n_digits(range.last, base) {
+            let tail_digits = n_digits / 2;
+            let tail_value = base.pow(tail_digits);
+            let smallest = if n_digits == 1 {
+                0
+            } else {
+                base.pow(n_digits - 1)
+            };
+            let largest = base.checked_pow(n_digits).map_or(Id::MAX, |p| p - 1);
+            let lo = range.first.max(smallest) / tail_value;
+            let hi = range.last.min(largest) / tail_value;
+            found.extend(
+                (lo..=hi)
+                    .filter_map(|head| {
+                        let mirrored =
+                            reverse_digits(head, tail_digits, n_digits - tail_digits, base);
+                        head.checked_mul(tail_value)?.checked_add(mirrored)
+                    })
+                    .filter(|id| range.contains(id)),
+            );
+        }
+        found
+    }
+
+    fn upside_down_in(&self, range: Range) -> Vec<Id> {
+        // Like a palindrome, determined by its leading half, but the tail
+        // repeats the leading digits turned by 180°
+        let base = self.base;
+        let mut found = Vec::new();
+        for n_digits in n_digits(range.first, base)..=n_digits(range.last, base) {
+            let tail_digits = n_digits / 2;
+            let tail_value = base.pow(tail_digits);
+            let smallest = if n_digits == 1 {
+                0
+            } else {
+                base.pow(n_digits - 1)
+            };
+            let largest = base.checked_pow(n_digits).map_or(Id::MAX, |p| p - 1);
+            let lo = range.first.max(smallest) / tail_value;
+            let hi = range.last.min(largest) / tail_value;
+            found.extend(
+                (lo..=hi)
+                    .filter_map(|head| {
+                        let turned =
+                            turned_digits(head, tail_digits, n_digits - tail_digits, base)?;
+                        head.checked_mul(tail_value)?.checked_add(turned)
+                    })
+                    .filter(|id| range.contains(id)),
+            );
+        }
+        found
+    }
+}
+
+impl Range {
+    fn contains(&self, id: &Id) -> bool {
+        self.first <= *id && *id <= self.last
+    }
+}
+
+fn upside_down(digit: Id) -> Option<Id> {
+    match digit {
+        0 | 1 | 8 => Some(digit),
+        6 => Some(9),
+        9 => Some(6),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+fn invalid_in_range(range: Range) -> Vec<Id> {
+    PART_1.invalid_in(range)
+}
+
+#[cfg(test)]
+fn invalid_in_range_2(range: Range) -> Vec<Id> {
+    PART_2.invalid_in(range)
+}
+
+#[cfg(test)]
+fn to_digits(mut id: Id, base: Id) -> Vec<Id> {
+    let mut digits = vec![id % base];
+    while id >= base {
+        id /= base;
+        digits.push(id % base);
+    }
+    digits.reverse();
+    digits
+}
+
+fn n_digits(id: Id, base: Id) -> u32 {
+    id.checked_ilog(base).unwrap_or(0) + 1
+}
+
+fn repunit(n_digits: u32, period: u32, base: Id) -> Option<Id> {
+    (0..n_digits / period).try_fold(0, |acc, i| base.checked_pow(period * i)?.checked_add(acc))
+}
+
+// The leading `n` of the `head_len` digits of `head`, in reverse order and
+// turned by 180°. None if a digit does not turn into one, or if a middle digit
+// beyond the leading `n` does not turn into itself
+fn turned_digits(head: Id, n: u32, head_len: u32, base: Id) -> Option<Id> {
+    let mut rest = head;
+    (0..head_len).try_fold(0, |acc, i| {
+        let digit = rest % base;
+        rest /= base;
+        let turned = upside_down(digit).filter(|&e| e < base)?;
+        if i < head_len - n {
+            (turned == digit).then_some(acc)
+        } else {
+            Some(acc * base + turned)
+        }
+    })
+}
+
+// The leading `n` of the `head_len` digits of `head`, in reverse order
+fn reverse_digits(head: Id, n: u32, head_len: u32, base: Id) -> Id {
+    let mut rest = head / base.pow(head_len - n);
+    (0..n).fold(0, |acc, _| {
+        let digit = rest % base;
+        rest /= base;
+        acc * base + digit
+    })
 }
 
 #[test]
@@ -144,6 +395,232 @@ fn test_invalid_in_range_end() {
     assert_eq!(invalid_in_range(range), vec![1010]);
 }
 
+#[test]
+fn test_invalid_in_range_excludes_last_plus_one() {
+    let range = Range {
+        first: 1000,
+        last: 1009,
+    };
+    assert_eq!(invalid_in_range(range), vec![]);
+    assert_eq!(invalid_in_range_2(range), vec![]);
+}
+
+#[test]
+fn test_merge_ranges() {
+    let merged = merge_ranges(parse_ranges("30-40,11-21,22-22,15-18,42-50"));
+    assert_eq!(
+        merged,
+        vec![
+            Range {
+                first: 11,
+                last: 22
+            },
+            Range {
+                first: 30,
+                last: 40
+            },
+            Range {
+                first: 42,
+                last: 50
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_sums_counts_overlaps_once() {
+    let sums = invalid_sums(parse_ranges("11-22,15-33,40-45"), &PART_1);
+    assert_eq!(sums.total(), 11 + 22 + 33 + 44);
+    let per_range: Vec<_> = sums.per_range.iter().map(|(_, sum)| sum.clone()).collect();
+    assert_eq!(per_range, vec![11 + 22 + 33, 44]);
+}
+
+#[test]
+fn test_repunit() {
+    assert_eq!(repunit(6, 2, 10), Some(10101));
+    assert_eq!(repunit(6, 3, 10), Some(1001));
+    assert_eq!(repunit(20, 10, 10), Some(10000000001));
+    assert_eq!(repunit(4, 2, 2), Some(0b101));
+}
+
+#[test]
+fn test_repeated_in_matches_brute_force() {
+    for (first, last) in [(1, 2000), (95, 115), (9_000, 1_200_000), (111_110, 111_112)] {
+        let range = Range { first, last };
+        let brute: Vec<Id> = (first..=last).filter(|&n| invalid(n)).collect();
+        assert_eq!(PART_1.invalid_in(range), brute);
+        let brute: Vec<Id> = (first..=last).filter(|&n| invalid_part_2(n)).collect();
+        assert_eq!(PART_2.invalid_in(range), brute);
+    }
+}
+
+#[test]
+fn test_repeated_in_wide() {
+    let range = Range {
+        first: 1,
+        last: 10_000_000_000,
+    };
+    assert_eq!(
+        PART_1.invalid_in(range).len(),
+        9 + 90 + 900 + 9_000 + 90_000
+    );
+    let range = Range {
+        first: 18_446_744_060_000_000_000,
+        last: u64::MAX.into(),
+    };
+    let found = PART_1.invalid_in(range);
+    assert_eq!(found, vec![18446744061844674406, 18446744071844674407]);
+}
+
+#[test]
+fn test_wide_ids() {
+    let largest_38_digits = Id::pow(10, 38) - 1;
+    let range = Range {
+        first: largest_38_digits - 10,
+        last: largest_38_digits + 11,
+    };
+    assert_eq!(PART_1.invalid_in(range), vec![largest_38_digits]);
+    assert_eq!(PART_2.invalid_in(range), vec![largest_38_digits]);
+
+    let range = Range {
+        first: Id::MAX - 1000,
+        last: Id::MAX,
+    };
+    assert_eq!(PART_2.invalid_in(range), vec![]);
+}
+
+#[test]
+fn test_wide_sum_does_not_overflow() {
+    let ranges = parse_ranges(
+        "99999999999999999909999999999999999990-99999999999999999999999999999999999999",
+    );
+    let sums = invalid_sums(ranges, &PART_1);
+    assert_eq!(
+        sums.total().to_string(),
+        "999999999999999999549999999999999999945"
+    );
+}
+
+#[test]
+fn test_rule_repeated() {
+    let three_times = InvalidRule {
+        pattern: Pattern::Repeated(3),
+        base: 10,
+    };
+    assert!(three_times.matches(123123123));
+    assert!(three_times.matches(777));
+    assert!(!three_times.matches(123123));
+    assert!(!three_times.matches(7777));
+
+    let at_least_three = InvalidRule {
+        pattern: Pattern::RepeatedAtLeast(3),
+        base: 10,
+    };
+    assert!(at_least_three.matches(7777));
+    assert!(at_least_three.matches(121212));
+    assert!(!at_least_three.matches(1212));
+}
+
+#[test]
+fn test_rule_base_2() {
+    let rule = InvalidRule {
+        pattern: Pattern::Repeated(2),
+        base: 2,
+    };
+    assert!(rule.matches(0b1010));
+    assert!(!rule.matches(0b1011));
+    assert!(rule.matches(0b110110));
+    let range = Range { first: 1, last: 64 };
+    assert_eq!(
+        rule.invalid_in(range),
+        vec![0b11, 0b1010, 0b1111, 0b100100, 0b101101, 0b110110, 0b111111]
+    );
+}
+
+#[test]
+fn test_rule_rejects_degenerate_rules() {
+    let pattern = Pattern::Repeated(2);
+    assert_eq!(
+        InvalidRule::new(pattern, 0),
+        Err("base 0 is not at least 2".into())
+    );
+    assert_eq!(
+        InvalidRule::new(pattern, 1),
+        Err("base 1 is not at least 2".into())
+    );
+    assert_eq!(
+        InvalidRule::new(pattern, 2),
+        Ok(InvalidRule { pattern, base: 2 })
+    );
+    assert_eq!(
+        InvalidRule::new(Pattern::Repeated(1), 10),
+        Err("repetition count 1 is not at least 2".into())
+    );
+    assert_eq!(
+        InvalidRule::new(Pattern::RepeatedAtLeast(0), 10),
+        Err("repetition count 0 is not at least 2".into())
+    );
+}
+
+#[test]
+fn test_rule_palindrome_and_upside_down() {
+    let palindrome = InvalidRule {
+        pattern: Pattern::Palindrome,
+        base: 10,
+    };
+    assert!(palindrome.matches(12321));
+    assert!(!palindrome.matches(12322));
+    let upside_down = InvalidRule {
+        pattern: Pattern::UpsideDown,
+        base: 10,
+    };
+    assert!(upside_down.matches(69));
+    assert!(upside_down.matches(818));
+    assert!(upside_down.matches(6009));
+    assert!(!upside_down.matches(66));
+}
+
+#[test]
+fn test_upside_down_in_long_ids() {
+    let rule = InvalidRule {
+        pattern: Pattern::UpsideDown,
+        base: 10,
+    };
+    let first = 10u128.pow(35);
+    let range = Range {
+        first,
+        last: first + 10,
+    };
+    assert_eq!(rule.invalid_in(range), vec![first + 1]);
+    let middle = 6 * 10u128.pow(37) + 10u128.pow(19) * 8;
+    let range = Range {
+        first: middle - 10u128.pow(19),
+        last: middle + 10u128.pow(19),
+    };
+    assert_eq!(
+        rule.invalid_in(range),
+        vec![60000000000000000088000000000000000009]
+    );
+}
+
+#[test]
+fn test_invalid_in_matches_brute_force() {
+    for base in [2, 3, 10, 16] {
+        for pattern in [
+            Pattern::Repeated(3),
+            Pattern::RepeatedAtLeast(2),
+            Pattern::Palindrome,
+            Pattern::UpsideDown,
+        ] {
+            let rule = InvalidRule::new(pattern, base).unwrap();
+            for (first, last) in [(0, 5_000), (987, 123_456)] {
+                let brute: Vec<Id> = (first..=last).filter(|&id| rule.matches(id)).collect();
+                assert_eq!(rule.invalid_in(Range { first, last }), brute, "{rule:?}");
+            }
+        }
+    }
+}
+
 #[test]
 fn test_solve_1_example() {
     assert_eq!(solve_1(EXAMPLE), 1227775554);
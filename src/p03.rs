@@ -1,3 +1,9 @@
+use crate::big_uint::BigUint;
+#[cfg(test)]
+use crate::helpers::Lcg;
+#[cfg(test)]
+use std::cmp::Reverse;
+
 pub fn solve(part2: bool) -> String {
     let input = std::fs::read_to_string("input_03.txt").expect("could not read file");
     if part2 {
@@ -7,11 +13,11 @@ pub fn solve(part2: bool) -> String {
     }
 }
 
-fn solve_1(input: &str) -> u64 {
+fn solve_1(input: &str) -> BigUint {
     input.lines().map(parse_bank).map(max_joltage).sum()
 }
 
-fn solve_2(input: &str) -> u64 {
+fn solve_2(input: &str) -> BigUint {
     input
         .lines()
         .map(parse_bank)
@@ -27,11 +33,129 @@ fn parse_bank(bank: &str) -> Bank {
         .collect()
 }
 
-fn max_joltage(bank: Bank) -> u64 {
+fn max_joltage(bank: Bank) -> BigUint {
     max_joltage_override(bank, 2)
 }
 
-fn max_joltage_override(bank: Bank, num_batteries: usize) -> u64 {
+fn max_joltage_override(bank: Bank, num_batteries: usize) -> BigUint {
+    let (_, joltage) = select_batteries(&bank, num_batteries);
+    joltage
+}
+
+type Selection = (Vec<usize>, BigUint); // (indices into the bank, ascending; joltage)
+
+fn select_batteries(bank: &Bank, num_batteries: usize) -> Selection {
+    // Monotonic stack: drop a smaller digit whenever a larger one follows, while we still may
+    let mut may_drop = bank.len() - num_batteries;
+    let mut positions: Vec<usize> = Vec::with_capacity(num_batteries);
+    for (i, &digit) in bank.iter().enumerate() {
+        while may_drop > 0 && positions.last().is_some_and(|&top| bank[top] < digit) {
+            positions.pop();
+            may_drop -= 1;
+        }
+        if positions.len() < num_batteries {
+            positions.push(i);
+        } else {
+            may_drop -= 1;
+        }
+    }
+
+    let digits: Vec<u64> = positions.iter().map(|&i| bank[i]).collect();
+    let joltage = BigUint::from_digits(&digits);
+    (positions, joltage)
+}
+
+#[cfg(test)]
+type FaultyBank = Vec<Option<u64>>; // None marks a faulty battery
+
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Goal {
+    Max,
+    Min,
+}
+
+#[cfg(test)]
+struct SelectionConstraints {
+    num_batteries: usize,
+    goal: Goal,
+    no_adjacent: bool, // never switch on two neighbouring batteries
+}
+
+#[cfg(test)]
+fn parse_faulty_bank(bank: &str) -> FaultyBank {
+    bank.chars()
+        .map(|b| b.to_digit(10).map(u64::from))
+        .collect()
+}
+
+#[cfg(test)]
+fn select_constrained(bank: &FaultyBank, constraints: &SelectionConstraints) -> Option<Selection> {
+    let gap = if constraints.no_adjacent { 2 } else { 1 };
+
+    // capacity[i]: most batteries that can still be switched on from index i onwards
+    let mut capacity = vec![0; bank.len() + gap];
+    for i in (0..bank.len()).rev() {
+        let with_i = if bank[i].is_some() {
+            1 + capacity[i + gap]
+        } else {
+            0
+        };
+        capacity[i] = capacity[i + 1].max(with_i);
+    }
+
+    // Best digit first, as early as possible, as long as the rest still fits behind it
+    let mut positions = Vec::with_capacity(constraints.num_batteries);
+    let mut start = 0;
+    for still_needed in (0..constraints.num_batteries).rev() {
+        let candidates =
+            (start..bank.len()).filter(|&i| bank[i].is_some() && capacity[i + gap] >= still_needed);
+        let best = match constraints.goal {
+            Goal::Max => candidates.max_by_key(|&i| (bank[i], Reverse(i))),
+            Goal::Min => candidates.min_by_key(|&i| (bank[i], i)),
+        }?;
+        positions.push(best);
+        start = best + gap;
+    }
+
+    let digits: Vec<u64> = positions.iter().map(|&i| bank[i].unwrap()).collect();
+    let joltage = BigUint::from_digits(&digits);
+    Some((positions, joltage))
+}
+
+#[cfg(test)]
+const HIGHLIGHT: &str = "\x1b[1;32m";
+#[cfg(test)]
+const RESET: &str = "\x1b[0m";
+
+#[cfg(test)]
+fn render_selection(bank: &Bank, (positions, joltage): &Selection) -> String {
+    let mut chosen = positions.iter().peekable();
+    let digits: String = bank
+        .iter()
+        .enumerate()
+        .map(|(i, digit)| {
+            if chosen.next_if_eq(&&i).is_some() {
+                format!("{HIGHLIGHT}{digit}{RESET}")
+            } else {
+                digit.to_string()
+            }
+        })
+        .collect();
+    format!("{digits} -> {joltage}")
+}
+
+#[cfg(test)]
+fn render_banks(input: &str, num_batteries: usize) -> String {
+    input
+        .lines()
+        .map(parse_bank)
+        .map(|bank| render_selection(&bank, &select_batteries(&bank, num_batteries)) + "\n")
+        .collect()
+}
+
+#[cfg(test)]
+fn max_joltage_rescan(bank: Bank, num_batteries: usize) -> u64 {
     let mut digits: Vec<u64> = Vec::new();
     let mut leftmost_possible_battery = 0;
 
@@ -90,6 +214,204 @@ fn test_max_joltage_override_2() {
     assert_eq!(max_joltage_override(bank, 12), 434234234278);
 }
 
+#[test]
+fn test_select_batteries() {
+    let bank = parse_bank("818181911112111");
+    assert_eq!(select_batteries(&bank, 2), (vec![6, 11], BigUint::from(92)));
+}
+
+#[test]
+fn test_select_batteries_repeats() {
+    let bank = parse_bank("9988");
+    assert_eq!(select_batteries(&bank, 2), (vec![0, 1], BigUint::from(99)));
+    assert_eq!(
+        select_batteries(&bank, 3),
+        (vec![0, 1, 2], BigUint::from(998))
+    );
+}
+
+#[test]
+fn test_render_selection() {
+    let bank = parse_bank("1918");
+    let rendered = render_selection(&bank, &select_batteries(&bank, 2));
+    assert_eq!(
+        rendered,
+        format!("1{HIGHLIGHT}9{RESET}1{HIGHLIGHT}8{RESET} -> 98")
+    );
+}
+
+#[test]
+fn test_render_banks() {
+    let rendered = render_banks(EXAMPLE, 12);
+    assert_eq!(rendered.lines().count(), 4);
+    assert!(
+        rendered
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(" -> 987654321111")
+    );
+    assert_eq!(rendered.matches(HIGHLIGHT).count(), 4 * 12);
+}
+
+#[test]
+fn test_max_joltage_override_many_batteries() {
+    let bank = parse_bank(&"9876543210".repeat(10));
+    let joltage = max_joltage_override(bank, 55);
+    let expected = "999999".to_string() + &"8765432109".repeat(4) + "876543210";
+    assert_eq!(joltage.to_string(), expected);
+}
+
+#[test]
+fn test_many_batteries_sum_exactly() {
+    let bank = "9".repeat(60);
+    let input = format!("{bank}\n{bank}\n");
+    let banks = input.lines().map(parse_bank);
+    let sum: BigUint = banks.map(|b| max_joltage_override(b, 50)).sum();
+    assert_eq!(sum.to_string(), "1".to_string() + &"9".repeat(49) + "8");
+}
+
+#[cfg(test)]
+fn pseudo_random_bank(len: usize, seed: u64) -> Bank {
+    let mut rng = Lcg::new(seed);
+    (0..len).map(|_| 1 + rng.below(9)).collect()
+}
+
+#[cfg(test)]
+fn select_brute_force(bank: &FaultyBank, constraints: &SelectionConstraints) -> Option<u128> {
+    (0u32..1 << bank.len())
+        .filter(|mask| mask.count_ones() as usize == constraints.num_batteries)
+        .filter(|mask| !constraints.no_adjacent || mask & (mask >> 1) == 0)
+        .filter_map(|mask| {
+            (0..bank.len())
+                .filter(|i| mask & (1 << i) != 0)
+                .try_fold(0, |acc, i| Some(acc * 10 + bank[i]? as u128))
+        })
+        .reduce(|a, b| match constraints.goal {
+            Goal::Max => a.max(b),
+            Goal::Min => a.min(b),
+        })
+}
+
+#[test]
+fn test_select_constrained_unconstrained_max() {
+    let constraints = SelectionConstraints {
+        num_batteries: 12,
+        goal: Goal::Max,
+        no_adjacent: false,
+    };
+    for line in EXAMPLE.lines() {
+        let selection = select_constrained(&parse_faulty_bank(line), &constraints);
+        assert_eq!(selection, Some(select_batteries(&parse_bank(line), 12)));
+    }
+}
+
+#[test]
+fn test_select_constrained_min() {
+    let constraints = SelectionConstraints {
+        num_batteries: 3,
+        goal: Goal::Min,
+        no_adjacent: false,
+    };
+    let (positions, joltage) =
+        select_constrained(&parse_faulty_bank("53142"), &constraints).unwrap();
+    assert_eq!(positions, vec![2, 3, 4]);
+    assert_eq!(joltage, 142);
+}
+
+#[test]
+fn test_select_constrained_faulty() {
+    let constraints = SelectionConstraints {
+        num_batteries: 2,
+        goal: Goal::Max,
+        no_adjacent: false,
+    };
+    let (positions, joltage) =
+        select_constrained(&parse_faulty_bank("19x8x"), &constraints).unwrap();
+    assert_eq!(positions, vec![1, 3]);
+    assert_eq!(joltage, 98);
+    assert_eq!(
+        select_constrained(&parse_faulty_bank("x9xx"), &constraints),
+        None
+    );
+}
+
+#[test]
+fn test_select_constrained_no_adjacent() {
+    let constraints = SelectionConstraints {
+        num_batteries: 2,
+        goal: Goal::Max,
+        no_adjacent: true,
+    };
+    let (positions, joltage) =
+        select_constrained(&parse_faulty_bank("1991"), &constraints).unwrap();
+    assert_eq!(positions, vec![1, 3]);
+    assert_eq!(joltage, 91);
+    assert_eq!(
+        select_constrained(&parse_faulty_bank("99"), &constraints),
+        None
+    );
+}
+
+#[test]
+fn test_select_constrained_matches_brute_force() {
+    for seed in 0..300 {
+        let mut bank: FaultyBank = pseudo_random_bank(4 + seed as usize % 9, seed)
+            .into_iter()
+            .map(Some)
+            .collect();
+        bank[seed as usize % 4] = None;
+        for goal in [Goal::Max, Goal::Min] {
+            for no_adjacent in [false, true] {
+                for num_batteries in 1..=4 {
+                    let constraints = SelectionConstraints {
+                        num_batteries,
+                        goal,
+                        no_adjacent,
+                    };
+                    let selection = select_constrained(&bank, &constraints)
+                        .map(|(_, joltage)| joltage.to_string());
+                    let expected = select_brute_force(&bank, &constraints).map(|j| j.to_string());
+                    assert_eq!(selection, expected);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_max_joltage_override_matches_rescan() {
+    for seed in 0..200 {
+        let bank = pseudo_random_bank(5 + seed as usize % 30, seed);
+        for num_batteries in [1, 2, 5, 12] {
+            if num_batteries <= bank.len() {
+                assert_eq!(
+                    max_joltage_override(bank.clone(), num_batteries),
+                    max_joltage_rescan(bank.clone(), num_batteries) as u128
+                );
+            }
+        }
+    }
+}
+
+#[ignore] // benchmark, run with --ignored --nocapture
+#[test]
+fn bench_max_joltage_override_million_digits() {
+    let bank = pseudo_random_bank(5_000_000, 42);
+    for num_batteries in [2, 12, 19] {
+        let start = std::time::Instant::now();
+        let stack = max_joltage_override(bank.clone(), num_batteries);
+        let stack_time = start.elapsed();
+        let start = std::time::Instant::now();
+        let rescan = max_joltage_rescan(bank.clone(), num_batteries);
+        let rescan_time = start.elapsed();
+        assert_eq!(stack, rescan as u128);
+        println!(
+            "{num_batteries} batteries: monotonic stack {stack_time:?}, rescan {rescan_time:?}"
+        );
+    }
+}
+
 #[cfg(test)]
 static EXAMPLE: &str = "987654321111111
 811111111111119
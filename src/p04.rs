@@ -1,76 +1,228 @@
-use std::collections::HashSet;
+#[cfg(test)]
+use crate::helpers::Lcg;
+use crate::helpers::{Grid, NEIGHBORS_4, NEIGHBORS_8, Pos};
+#[cfg(test)]
+use std::iter;
 
 pub fn solve(part2: bool) -> String {
     let input = std::fs::read_to_string("input_04.txt").expect("could not read file");
     if part2 {
-        solve_2(&input).to_string()
+        solve_2(&input, &FORKLIFT).to_string()
     } else {
-        solve_1(&input).to_string()
+        solve_1(&input, &FORKLIFT).to_string()
     }
 }
 
-fn solve_1(input: &str) -> usize {
+fn solve_1(input: &str, rule: &AccessRule) -> usize {
     let diagram = parse_diagram(input);
-    diagram
-        .iter()
-        .filter(|&roll| accessible(&diagram, roll))
+    rolls(&diagram)
+        .filter(|roll| accessible(&diagram, roll, rule))
         .count()
 }
 
-fn solve_2(input: &str) -> usize {
-    let mut diagram = parse_diagram(input);
-    let inital_count = diagram.len();
+fn solve_2(input: &str, rule: &AccessRule) -> usize {
+    count_removable(&parse_diagram(input), rule)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(dead_code)] // the puzzle itself only uses the Moore neighborhood
+enum Neighborhood {
+    Moore,      // the 8 surrounding cells
+    VonNeumann, // the 4 orthogonal cells
+    Custom(Vec<(isize, isize)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)] // the puzzle itself only uses bounded edges
+enum Edges {
+    Bounded,
+    Toroidal,
+}
+
+// A roll is accessible when fewer than `threshold` neighbors are rolls
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AccessRule {
+    neighborhood: Neighborhood,
+    threshold: usize,
+    edges: Edges,
+}
+
+const FORKLIFT: AccessRule = AccessRule {
+    neighborhood: Neighborhood::Moore,
+    threshold: 4,
+    edges: Edges::Bounded,
+};
+
+impl AccessRule {
+    fn offsets(&self) -> &[(isize, isize)] {
+        match &self.neighborhood {
+            Neighborhood::Moore => &NEIGHBORS_8,
+            Neighborhood::VonNeumann => &NEIGHBORS_4,
+            Neighborhood::Custom(offsets) => offsets,
+        }
+    }
+
+    fn neighbors(&self, diagram: &Diagram, roll: Roll) -> Vec<Roll> {
+        self.shifted(diagram, roll, 1)
+    }
+
+    // Positions which have `roll` among their neighbors, differs from
+    // `neighbors` for asymmetric custom offsets
+    fn neighbors_of(&self, diagram: &Diagram, roll: Roll) -> Vec<Roll> {
+        self.shifted(diagram, roll, -1)
+    }
+
+    fn shifted(&self, diagram: &Diagram, roll: Roll, sign: isize) -> Vec<Roll> {
+        let mut positions = Vec::new();
+        for &(dy, dx) in self.offsets() {
+            let delta = (sign * dy, sign * dx);
+            let pos = match self.edges {
+                Edges::Bounded => diagram.offset(roll, delta),
+                Edges::Toroidal => Some(diagram.offset_wrapping(roll, delta)),
+            };
+            // on narrow tori offsets can wrap back onto the roll or onto each other
+            if let Some(pos) = pos.filter(|&pos| pos != roll && !positions.contains(&pos)) {
+                positions.push(pos);
+            }
+        }
+        positions
+    }
+}
+
+#[cfg(test)]
+fn count_removable_in_rounds(diagram: &Diagram, rule: &AccessRule) -> usize {
+    let mut diagram = diagram.clone();
+    let inital_count = rolls(&diagram).count();
     let mut last_count = usize::MAX;
 
-    while diagram.len() < last_count {
-        last_count = diagram.len();
-        diagram = remove_accessible(diagram)
+    while rolls(&diagram).count() < last_count {
+        last_count = rolls(&diagram).count();
+        diagram = remove_accessible(diagram, rule)
     }
     inital_count - last_count
 }
 
-type Roll = (usize, usize);
-type Diagram = HashSet<Roll>;
+type Roll = Pos;
+type Diagram = Grid<bool>; // true where there is a roll
 
 fn parse_diagram(diagram: &str) -> Diagram {
-    diagram
-        .lines()
-        .enumerate()
-        .flat_map(|(y, line)| line.chars().enumerate().map(move |(x, c)| (y, x, c)))
-        .filter_map(|(y, x, c)| if c == '@' { Some((y, x)) } else { None })
-        .collect()
+    Grid::parse(diagram, |c| c == '@')
 }
 
-fn accessible(diagram: &Diagram, roll: &Roll) -> bool {
-    let (y, x) = (roll.0, roll.1);
-    let deltas = [
-        (-1, -1),
-        (-1, 0),
-        (-1, 1),
-        (0, -1),
-        (0, 1),
-        (1, -1),
-        (1, 0),
-        (1, 1),
-    ];
-    let neighbors = deltas
-        .iter()
-        .filter(|&&delta| {
-            // TODO: this goes wrong when there are rolls at usize.MAX
-            diagram.contains(&(
-                y.wrapping_add_signed(delta.0),
-                x.wrapping_add_signed(delta.1),
-            ))
-        })
+fn rolls(diagram: &Diagram) -> impl Iterator<Item = Roll> + '_ {
+    diagram.positions().filter(|&pos| diagram[pos])
+}
+
+fn accessible(diagram: &Diagram, roll: &Roll, rule: &AccessRule) -> bool {
+    let neighbors = rule
+        .neighbors(diagram, *roll)
+        .into_iter()
+        .filter(|&pos| diagram[pos])
         .count();
-    neighbors < 4
+    neighbors < rule.threshold
 }
 
-fn remove_accessible(diagram: Diagram) -> Diagram {
-    diagram
+#[cfg(test)]
+fn remove_accessible(diagram: Diagram, rule: &AccessRule) -> Diagram {
+    let mut removed = diagram.clone();
+    rolls(&diagram)
+        .filter(|roll| accessible(&diagram, roll, rule))
+        .for_each(|roll| removed[roll] = false);
+    removed
+}
+
+fn count_removable(diagram: &Diagram, rule: &AccessRule) -> usize {
+    let waves = removal_waves(diagram, rule);
+    rolls(diagram).filter(|&roll| waves[roll].is_some()).count()
+}
+
+type Waves = Grid<Option<usize>>; // wave (from 1) in which each roll is removed
+
+fn removal_waves(diagram: &Diagram, rule: &AccessRule) -> Waves {
+    // Worklist: removing a roll only ever frees up rolls it neighbors
+    let mut neighbor_counts = Grid::new(diagram.width, diagram.height, 0);
+    for roll in rolls(diagram) {
+        neighbor_counts[roll] = rule
+            .neighbors(diagram, roll)
+            .into_iter()
+            .filter(|&pos| diagram[pos])
+            .count();
+    }
+
+    let mut waves = Grid::new(diagram.width, diagram.height, None);
+    let mut this_wave: Vec<Roll> = rolls(diagram)
+        .filter(|&roll| neighbor_counts[roll] < rule.threshold)
+        .collect();
+    let mut wave = 1;
+    while !this_wave.is_empty() {
+        this_wave.iter().for_each(|&roll| waves[roll] = Some(wave));
+        let mut next_wave = Vec::new();
+        for roll in this_wave {
+            for neighbor in rule.neighbors_of(diagram, roll) {
+                if diagram[neighbor] && waves[neighbor].is_none() {
+                    neighbor_counts[neighbor] -= 1;
+                    if neighbor_counts[neighbor] + 1 == rule.threshold {
+                        next_wave.push(neighbor);
+                    }
+                }
+            }
+        }
+        this_wave = next_wave;
+        wave += 1;
+    }
+    waves
+}
+
+#[cfg(test)]
+fn wave_table(waves: &Waves) -> String {
+    let mut counts: Vec<usize> = Vec::new();
+    for wave in waves.positions().filter_map(|pos| waves[pos]) {
+        if counts.len() < wave {
+            counts.resize(wave, 0);
+        }
+        counts[wave - 1] += 1;
+    }
+    let rows = counts
         .iter()
-        .filter(|&roll| !accessible(&diagram, roll))
-        .cloned()
+        .zip(1..)
+        .map(|(count, wave)| format!("{wave:>4} {count:>7}\n"));
+    iter::once("wave removed\n".to_string())
+        .chain(rows)
+        .collect()
+}
+
+#[cfg(test)]
+fn wave_grid(diagram: &Diagram, waves: &Waves) -> String {
+    // waves as base-36 digits, '@' for rolls that stay
+    let mut cells = diagram.map(|&roll| if roll { '@' } else { '.' });
+    for pos in waves.positions() {
+        if let Some(wave) = waves[pos] {
+            cells[pos] = char::from_digit(wave as u32, 36).unwrap_or('#');
+        }
+    }
+    cells.to_string()
+}
+
+#[cfg(test)]
+fn wave_frames(diagram: &Diagram, waves: &Waves) -> Vec<String> {
+    // frame n: 'x' for rolls removed in wave n, '.' for earlier ones
+    let last_wave = waves
+        .positions()
+        .filter_map(|pos| waves[pos])
+        .max()
+        .unwrap_or(0);
+    (0..=last_wave)
+        .map(|frame| {
+            let mut cells = diagram.map(|&roll| if roll { '@' } else { '.' });
+            for pos in waves.positions() {
+                match waves[pos] {
+                    Some(wave) if wave < frame => cells[pos] = '.',
+                    Some(wave) if wave == frame => cells[pos] = 'x',
+                    _ => {}
+                }
+            }
+            cells.to_string()
+        })
         .collect()
 }
 
@@ -90,28 +242,201 @@ static EXAMPLE1: &str = "..@@.@@@@.
 #[test]
 fn test_parse_diagram() {
     let diagram = parse_diagram(EXAMPLE1);
-    assert_eq!(diagram.len(), 71);
-    assert!(!diagram.contains(&(0, 0)));
-    assert!(diagram.contains(&(1, 1)));
+    assert_eq!(rolls(&diagram).count(), 71);
+    assert!(!diagram[(0, 0)]);
+    assert!(diagram[(1, 1)]);
 }
 
 #[test]
 fn test_accessible() {
     let diagram = parse_diagram(EXAMPLE1);
-    assert!(accessible(&diagram, &(0, 2)));
-    assert!(!accessible(&diagram, &(1, 1)));
+    assert!(accessible(&diagram, &(0, 2), &FORKLIFT));
+    assert!(!accessible(&diagram, &(1, 1), &FORKLIFT));
 }
 #[test]
 fn test_remove_accessible() {
-    let removed_once = remove_accessible(parse_diagram(EXAMPLE1));
-    assert_eq!(removed_once.len(), 71 - 13);
-    let removed_twice = remove_accessible(removed_once);
-    assert_eq!(removed_twice.len(), 71 - 13 - 12);
+    let removed_once = remove_accessible(parse_diagram(EXAMPLE1), &FORKLIFT);
+    assert_eq!(rolls(&removed_once).count(), 71 - 13);
+    let removed_twice = remove_accessible(removed_once, &FORKLIFT);
+    assert_eq!(rolls(&removed_twice).count(), 71 - 13 - 12);
+}
+
+#[cfg(test)]
+fn pseudo_random_diagram(width: usize, height: usize, seed: u64) -> Diagram {
+    let mut rng = Lcg::new(seed);
+    let mut diagram = Grid::new(width, height, false);
+    for pos in diagram.positions() {
+        diagram[pos] = rng.below(10) < 7;
+    }
+    diagram
+}
+
+#[test]
+fn test_count_removable() {
+    assert_eq!(count_removable(&parse_diagram(EXAMPLE1), &FORKLIFT), 43);
+    assert_eq!(count_removable(&parse_diagram(""), &FORKLIFT), 0);
+}
+
+#[test]
+fn test_count_removable_matches_rounds() {
+    for seed in 0..50 {
+        let diagram = pseudo_random_diagram(5 + seed as usize % 20, 3 + seed as usize % 17, seed);
+        assert_eq!(
+            count_removable(&diagram, &FORKLIFT),
+            count_removable_in_rounds(&diagram, &FORKLIFT)
+        );
+    }
+}
+
+#[test]
+fn test_removal_waves() {
+    let diagram = parse_diagram(EXAMPLE1);
+    let waves = removal_waves(&diagram, &FORKLIFT);
+    assert_eq!(waves[(0, 2)], Some(1));
+    assert_eq!(waves[(0, 0)], None);
+    let first_wave = rolls(&diagram).filter(|&r| waves[r] == Some(1)).count();
+    assert_eq!(first_wave, 13);
+}
+
+#[test]
+fn test_removal_waves_match_rounds() {
+    let mut diagram = parse_diagram(EXAMPLE1);
+    let waves = removal_waves(&diagram, &FORKLIFT);
+    for wave in 1..10 {
+        let next = remove_accessible(diagram.clone(), &FORKLIFT);
+        let removed: Vec<Roll> = rolls(&diagram).filter(|&r| !next[r]).collect();
+        assert!(removed.iter().all(|&r| waves[r] == Some(wave)));
+        let in_wave = waves
+            .positions()
+            .filter(|&p| waves[p] == Some(wave))
+            .count();
+        assert_eq!(removed.len(), in_wave);
+        diagram = next;
+    }
+}
+
+#[test]
+fn test_wave_table() {
+    let table = wave_table(&removal_waves(&parse_diagram(EXAMPLE1), &FORKLIFT));
+    let mut lines = table.lines();
+    assert_eq!(lines.next(), Some("wave removed"));
+    assert_eq!(lines.next(), Some("   1      13"));
+    assert_eq!(lines.next(), Some("   2      12"));
+    assert_eq!(table.lines().count(), 1 + 9);
+}
+
+#[test]
+fn test_wave_grid() {
+    let diagram = parse_diagram(".@@@\n@@@@\n.@@.\n");
+    let grid = wave_grid(&diagram, &removal_waves(&diagram, &FORKLIFT));
+    assert_eq!(grid, ".231\n1332\n.23.\n");
+}
+
+#[test]
+fn test_wave_frames() {
+    let diagram = parse_diagram(".@@@\n@@@@\n.@@.\n");
+    let frames = wave_frames(&diagram, &removal_waves(&diagram, &FORKLIFT));
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[0], ".@@@\n@@@@\n.@@.\n");
+    assert_eq!(frames[1], ".@@x\nx@@@\n.@@.\n");
+    assert_eq!(frames[3], "..x.\n.xx.\n..x.\n");
+}
+
+#[test]
+fn test_rule_neighbors() {
+    let diagram = parse_diagram("...\n...\n...\n");
+    let von_neumann = AccessRule {
+        neighborhood: Neighborhood::VonNeumann,
+        ..FORKLIFT
+    };
+    assert_eq!(
+        von_neumann.neighbors(&diagram, (0, 0)),
+        vec![(0, 1), (1, 0)]
+    );
+    let torus = AccessRule {
+        edges: Edges::Toroidal,
+        ..von_neumann
+    };
+    assert_eq!(
+        torus.neighbors(&diagram, (0, 0)),
+        vec![(2, 0), (0, 2), (0, 1), (1, 0)]
+    );
+    let right = AccessRule {
+        neighborhood: Neighborhood::Custom(vec![(0, 1), (0, 2)]),
+        ..FORKLIFT
+    };
+    assert_eq!(right.neighbors(&diagram, (1, 0)), vec![(1, 1), (1, 2)]);
+    assert_eq!(right.neighbors_of(&diagram, (1, 1)), vec![(1, 0)]);
+}
+
+#[test]
+fn test_rule_narrow_torus() {
+    let torus = AccessRule {
+        edges: Edges::Toroidal,
+        ..FORKLIFT
+    };
+    let diagram = parse_diagram("@@\n@@\n");
+    let mut neighbors = torus.neighbors(&diagram, (0, 0));
+    neighbors.sort();
+    assert_eq!(neighbors, vec![(0, 1), (1, 0), (1, 1)]);
+    assert_eq!(solve_1("@@\n@@\n", &torus), 4);
+    assert_eq!(solve_2("@@\n@@\n", &torus), 4);
+    let column = parse_diagram("@\n@\n@\n");
+    assert_eq!(torus.neighbors(&column, (0, 0)), vec![(2, 0), (1, 0)]);
+}
+
+#[test]
+fn test_accessible_rules() {
+    let diagram = parse_diagram("@@@\n@@@\n@@@\n");
+    let von_neumann = AccessRule {
+        neighborhood: Neighborhood::VonNeumann,
+        threshold: 3,
+        edges: Edges::Bounded,
+    };
+    assert!(accessible(&diagram, &(0, 0), &von_neumann));
+    assert!(!accessible(&diagram, &(0, 1), &von_neumann));
+    let torus = AccessRule {
+        edges: Edges::Toroidal,
+        ..von_neumann.clone()
+    };
+    assert!(!accessible(&diagram, &(0, 0), &torus));
+    assert_eq!(solve_1("@@@\n@@@\n@@@\n", &torus), 0);
+    assert_eq!(solve_1("@@@\n@@@\n@@@\n", &von_neumann), 4);
+}
+
+#[test]
+fn test_removal_waves_match_rounds_for_rules() {
+    let rules = [
+        AccessRule {
+            neighborhood: Neighborhood::VonNeumann,
+            threshold: 3,
+            edges: Edges::Toroidal,
+        },
+        AccessRule {
+            neighborhood: Neighborhood::Moore,
+            threshold: 5,
+            edges: Edges::Toroidal,
+        },
+        AccessRule {
+            neighborhood: Neighborhood::Custom(vec![(0, 1), (0, 2), (1, 1), (-2, 0)]),
+            threshold: 2,
+            edges: Edges::Bounded,
+        },
+    ];
+    for rule in &rules {
+        for seed in 0..30 {
+            let diagram = pseudo_random_diagram(2 + seed as usize % 9, 1 + seed as usize % 7, seed);
+            assert_eq!(
+                count_removable(&diagram, rule),
+                count_removable_in_rounds(&diagram, rule)
+            );
+        }
+    }
 }
 
 #[test]
 fn test_solve_1_example() {
-    assert_eq!(solve_1(EXAMPLE1), 13);
+    assert_eq!(solve_1(EXAMPLE1, &FORKLIFT), 13);
 }
 
 #[test]
@@ -121,7 +446,7 @@ fn test_solve_1() {
 
 #[test]
 fn test_solve_2_example() {
-    assert_eq!(solve_2(EXAMPLE1), 43);
+    assert_eq!(solve_2(EXAMPLE1, &FORKLIFT), 43);
 }
 
 #[test]
@@ -1,4 +1,6 @@
-use std::iter;
+#[cfg(test)]
+use crate::helpers::Lcg;
+use crate::interval_set::IntervalSet;
 use std::vec::Vec;
 
 pub fn solve(part2: bool) -> String {
@@ -12,68 +14,48 @@ pub fn solve(part2: bool) -> String {
 
 fn solve_1(input: &str) -> usize {
     let (ranges, ingredients) = parse(input);
-    let fresh_ingredients = ingredients
-        .iter()
-        .filter(|i| ranges.iter().any(|r| r.contains(i)));
-    fresh_ingredients.count()
+    count_fresh(&fresh_set(&ranges), ingredients)
 }
 
-fn solve_2(input: &str) -> u64 {
-    let (ranges, _) = parse(input);
-    let mut union = Vec::new();
-
-    for r in ranges {
-        union = union_into(&union, &r.clone());
-    }
-
-    union.iter().map(|r| r.end - r.start + 1).sum()
+fn count_fresh(fresh: &IntervalSet<Id>, mut ingredients: Vec<Id>) -> usize {
+    // sorted ingredients can be swept against the sorted ranges in one pass
+    ingredients.sort_unstable();
+    fresh.count_sorted(&ingredients)
 }
 
-fn union_into(ranges: &Vec<FreshRange>, new_range: &FreshRange) -> Vec<FreshRange> {
-    let ranges_to_union: Vec<FreshRange> = ranges
+#[cfg(test)]
+fn count_fresh_scan(ranges: &[FreshRange], ingredients: &[Id]) -> usize {
+    ingredients
         .iter()
-        .filter(|existing| existing.intersects(new_range))
-        .chain(iter::once(new_range))
-        .cloned()
-        .collect();
-
-    let lower_bound = ranges_to_union.iter().map(|r| r.start).min().unwrap();
-    let upper_bound = ranges_to_union.iter().map(|r| r.end).max().unwrap();
+        .filter(|i| ranges.iter().any(|r| r.contains(i)))
+        .count()
+}
 
-    let new_union = FreshRange {
-        start: lower_bound,
-        end: upper_bound,
-    };
+fn solve_2(input: &str) -> u128 {
+    let (ranges, _) = parse(input);
+    fresh_set(&ranges).total_len()
+}
 
-    ranges
-        .iter()
-        .filter(|existing| !existing.intersects(new_range))
-        .chain(iter::once(&new_union))
-        .cloned()
-        .collect()
+pub(crate) fn fresh_set(ranges: &[FreshRange]) -> IntervalSet<Id> {
+    ranges.iter().map(|r| r.start..=r.end).collect()
 }
 
-type Id = u64;
+pub(crate) type Id = u64;
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
-struct FreshRange {
-    start: Id,
-    end: Id,
+pub(crate) struct FreshRange {
+    pub(crate) start: Id,
+    pub(crate) end: Id,
 }
 
 impl FreshRange {
+    #[cfg(test)]
     fn contains(&self, id: &Id) -> bool {
         self.start <= *id && *id <= self.end
     }
-
-    fn intersects(&self, other: &FreshRange) -> bool {
-        let subset = other.start < self.start && other.end > self.end;
-        let superset = other.start > self.start && other.end < self.end;
-        self.contains(&other.end) | self.contains(&other.start) | subset | superset
-    }
 }
 
-fn parse(input: &str) -> (Vec<FreshRange>, Vec<Id>) {
+pub(crate) fn parse(input: &str) -> (Vec<FreshRange>, Vec<Id>) {
     let (ranges_raw, ingredients_raw) = input.split_once("\n\n").unwrap();
     let ranges = ranges_raw
         .lines()
@@ -91,7 +73,66 @@ fn parse(input: &str) -> (Vec<FreshRange>, Vec<Id>) {
 }
 
 #[cfg(test)]
-static EXAMPLE: &str = "3-5
+type Line = usize; // line of a range in the input, from 1
+
+#[cfg(test)]
+#[derive(Debug, PartialEq, Eq)]
+enum Freshness {
+    Fresh(Vec<Line>), // every range containing the ingredient
+    Spoiled {
+        below: Option<Line>, // range ending closest before the ingredient
+        above: Option<Line>, // range starting closest after the ingredient
+    },
+}
+
+#[cfg(test)]
+fn explain(ranges: &[FreshRange], id: Id) -> Freshness {
+    let numbered = || ranges.iter().zip(1..);
+    let containing: Vec<Line> = numbered()
+        .filter(|(r, _)| r.contains(&id))
+        .map(|(_, line)| line)
+        .collect();
+    if !containing.is_empty() {
+        return Freshness::Fresh(containing);
+    }
+    // ties go to the earlier line
+    let below = numbered()
+        .filter(|(r, _)| r.end < id)
+        .min_by_key(|&(r, line)| (id - r.end, line))
+        .map(|(_, line)| line);
+    let above = numbered()
+        .filter(|(r, _)| r.start > id)
+        .min_by_key(|&(r, line)| (r.start - id, line))
+        .map(|(_, line)| line);
+    Freshness::Spoiled { below, above }
+}
+
+#[cfg(test)]
+fn explain_report(input: &str) -> String {
+    let (ranges, ingredients) = parse(input);
+    let describe = |line: Line| {
+        let r = &ranges[line - 1];
+        format!("line {line} ({}-{})", r.start, r.end)
+    };
+    let describe_nearest = |line: Option<Line>| line.map_or("none".to_string(), describe);
+    ingredients
+        .iter()
+        .map(|&id| match explain(&ranges, id) {
+            Freshness::Fresh(lines) => {
+                let lines: Vec<String> = lines.into_iter().map(describe).collect();
+                format!("{id} fresh: {}\n", lines.join(", "))
+            }
+            Freshness::Spoiled { below, above } => format!(
+                "{id} spoiled: below {}, above {}\n",
+                describe_nearest(below),
+                describe_nearest(above)
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub(crate) static EXAMPLE: &str = "3-5
 10-14
 16-20
 12-18
@@ -113,6 +154,124 @@ fn test_parse() {
     assert!(ingredients.contains(&8));
 }
 
+#[cfg(test)]
+fn pseudo_random_inventory(
+    num_ranges: usize,
+    num_ingredients: usize,
+    seed: u64,
+) -> (Vec<FreshRange>, Vec<Id>) {
+    let mut rng = Lcg::new(seed);
+    let mut next = |n| rng.below(n);
+    let max_id = 1 << 30;
+    let ranges = (0..num_ranges)
+        .map(|_| {
+            let start = next(max_id);
+            FreshRange {
+                start,
+                end: start + next(max_id / num_ranges as u64),
+            }
+        })
+        .collect();
+    let ingredients = (0..num_ingredients).map(|_| next(max_id)).collect();
+    (ranges, ingredients)
+}
+
+#[test]
+fn test_count_fresh_matches_scan() {
+    for seed in 0..20 {
+        let (ranges, ingredients) = pseudo_random_inventory(1 + seed as usize, 200, seed);
+        let fresh = fresh_set(&ranges);
+        let expected = count_fresh_scan(&ranges, &ingredients);
+        assert_eq!(count_fresh(&fresh, ingredients.clone()), expected);
+        let looked_up = ingredients.iter().filter(|&&i| fresh.contains(i)).count();
+        assert_eq!(looked_up, expected);
+    }
+}
+
+#[ignore] // benchmark, run with --ignored --nocapture
+#[test]
+fn bench_count_fresh_million_ingredients() {
+    let (ranges, ingredients) = pseudo_random_inventory(100_000, 1_000_000, 42);
+    let start = std::time::Instant::now();
+    let fresh = fresh_set(&ranges);
+    let merge_time = start.elapsed();
+    let start = std::time::Instant::now();
+    let looked_up = ingredients.iter().filter(|&&i| fresh.contains(i)).count();
+    let lookup_time = start.elapsed();
+    let start = std::time::Instant::now();
+    let swept = count_fresh(&fresh, ingredients.clone());
+    let sweep_time = start.elapsed();
+    assert_eq!(looked_up, swept);
+    // the scan is too slow for all ingredients, time a sample
+    let sample = &ingredients[..1000];
+    let start = std::time::Instant::now();
+    let scanned = count_fresh_scan(&ranges, sample);
+    let scan_time = start.elapsed();
+    assert_eq!(
+        scanned,
+        sample.iter().filter(|&&i| fresh.contains(i)).count()
+    );
+    println!(
+        "merge {merge_time:?}, binary search {lookup_time:?}, sorted sweep {sweep_time:?}, \
+         scan of 1000 {scan_time:?}"
+    );
+}
+
+#[test]
+fn test_explain() {
+    let (ranges, _) = parse(EXAMPLE);
+    assert_eq!(explain(&ranges, 5), Freshness::Fresh(vec![1]));
+    assert_eq!(explain(&ranges, 17), Freshness::Fresh(vec![3, 4]));
+    assert_eq!(
+        explain(&ranges, 8),
+        Freshness::Spoiled {
+            below: Some(1),
+            above: Some(2)
+        }
+    );
+    assert_eq!(
+        explain(&ranges, 1),
+        Freshness::Spoiled {
+            below: None,
+            above: Some(1)
+        }
+    );
+    assert_eq!(
+        explain(&ranges, 32),
+        Freshness::Spoiled {
+            below: Some(3),
+            above: None
+        }
+    );
+}
+
+#[test]
+fn test_explain_agrees_with_count() {
+    for seed in 0..20 {
+        let (ranges, ingredients) = pseudo_random_inventory(1 + seed as usize, 100, seed);
+        let fresh = ingredients
+            .iter()
+            .filter(|&&id| matches!(explain(&ranges, id), Freshness::Fresh(_)))
+            .count();
+        assert_eq!(fresh, count_fresh_scan(&ranges, &ingredients));
+    }
+}
+
+#[test]
+fn test_explain_report() {
+    let report = explain_report(EXAMPLE);
+    assert_eq!(
+        report,
+        "1 spoiled: below none, above line 1 (3-5)
+5 fresh: line 1 (3-5)
+8 spoiled: below line 1 (3-5), above line 2 (10-14)
+11 fresh: line 2 (10-14)
+17 fresh: line 3 (16-20), line 4 (12-18)
+32 spoiled: below line 3 (16-20), above none
+"
+    );
+}
+
 #[test]
 fn test_solve_1_example() {
     assert_eq!(solve_1(EXAMPLE), 3);
@@ -128,57 +287,55 @@ fn test_solve_2_example() {
     assert_eq!(solve_2(EXAMPLE), 14);
 }
 
+#[cfg(test)]
+fn union_of(ranges: &[FreshRange]) -> Vec<FreshRange> {
+    fresh_set(ranges)
+        .iter()
+        .map(|r| FreshRange {
+            start: *r.start(),
+            end: *r.end(),
+        })
+        .collect()
+}
+
 #[test]
-fn test_solve_2_union_into_fill_gap() {
-    let with_gap = vec![
+fn test_solve_2_union_fill_gap() {
+    let ranges = vec![
         FreshRange { start: 0, end: 10 },
         FreshRange { start: 20, end: 30 },
+        FreshRange { start: 10, end: 20 },
     ];
-
-    let middle = FreshRange { start: 10, end: 20 };
-    let union = union_into(&with_gap, &middle);
-    assert_eq!(union, vec![FreshRange { start: 0, end: 30 }]);
+    assert_eq!(union_of(&ranges), vec![FreshRange { start: 0, end: 30 }]);
 }
 
-#[ignore] // not necessary, makes implementation complicated
 #[test]
-fn test_solve_2_union_into_fill_gap_non_overlapping() {
-    let with_gap = vec![
+fn test_solve_2_union_fill_gap_non_overlapping() {
+    let ranges = vec![
         FreshRange { start: 0, end: 10 },
         FreshRange { start: 20, end: 30 },
+        FreshRange { start: 11, end: 19 },
     ];
-
-    let middle = FreshRange { start: 11, end: 19 };
-    let union = union_into(&with_gap, &middle);
-    assert_eq!(union, vec![FreshRange { start: 0, end: 30 }]);
+    assert_eq!(union_of(&ranges), vec![FreshRange { start: 0, end: 30 }]);
 }
 
 #[test]
-fn test_solve_2_union_into_respects_gaps_of_size_1() {
-    let with_gap = vec![
+fn test_solve_2_union_respects_gaps_of_size_1() {
+    let ranges = vec![
         FreshRange { start: 0, end: 10 },
         FreshRange { start: 20, end: 30 },
+        FreshRange { start: 12, end: 18 },
     ];
-
-    let middle = FreshRange { start: 12, end: 18 };
-    let union = union_into(&with_gap, &middle);
-    assert_eq!(union.len(), 3);
-}
-
-#[test]
-fn test_solve_2_union_into_consumes_subsets() {
-    let subset = FreshRange { start: 10, end: 12 };
-    let superset = FreshRange { start: 1, end: 22 };
-    let union = union_into(&vec![subset], &superset);
-    assert_eq!(union, vec!(superset));
+    assert_eq!(union_of(&ranges).len(), 3);
 }
 
 #[test]
-fn test_solve_2_union_into_consumes_subsets_reverse() {
+fn test_solve_2_union_consumes_subsets() {
     let subset = FreshRange { start: 10, end: 12 };
     let superset = FreshRange { start: 1, end: 22 };
-    let union = union_into(&vec![superset.clone()], &subset);
-    assert_eq!(union, vec!(superset));
+    let union = union_of(&[subset.clone(), superset.clone()]);
+    assert_eq!(union, vec![superset.clone()]);
+    let union = union_of(&[superset.clone(), subset]);
+    assert_eq!(union, vec![superset]);
 }
 
 #[test]
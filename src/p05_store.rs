@@ -0,0 +1,332 @@
+#[cfg(test)]
+use crate::helpers::Lcg;
+use crate::interval_set::IntervalSet;
+#[cfg(test)]
+use crate::p05::EXAMPLE;
+use crate::p05::{FreshRange, Id, fresh_set, parse};
+use std::collections::BTreeMap;
+use std::collections::btree_map::Entry;
+use std::fs;
+use std::io::Write;
+use std::iter;
+use std::path::Path;
+
+// Freshness database kept as a line-oriented change log:
+//
+//     fresh-db 1
+//     +range 3-5
+//     +ingredient 5
+//     -range 3-5
+//
+// Changes are appended to the file, loading replays them in order.
+
+const HEADER: &str = "fresh-db";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    AddRange(FreshRange),
+    RemoveRange(FreshRange),
+    AddIngredient(Id),
+    RemoveIngredient(Id),
+}
+
+impl Change {
+    fn to_line(&self) -> String {
+        match self {
+            Change::AddRange(r) => format!("+range {}-{}", r.start, r.end),
+            Change::RemoveRange(r) => format!("-range {}-{}", r.start, r.end),
+            Change::AddIngredient(id) => format!("+ingredient {id}"),
+            Change::RemoveIngredient(id) => format!("-ingredient {id}"),
+        }
+    }
+
+    fn parse(line: &str) -> Option<Change> {
+        let (kind, value) = line.split_once(' ')?;
+        let range = || {
+            let (start, end) = value.split_once('-')?;
+            Some(FreshRange {
+                start: start.parse().ok()?,
+                end: end.parse().ok()?,
+            })
+        };
+        match kind {
+            "+range" => range().map(Change::AddRange),
+            "-range" => range().map(Change::RemoveRange),
+            "+ingredient" => value.parse().ok().map(Change::AddIngredient),
+            "-ingredient" => value.parse().ok().map(Change::RemoveIngredient),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FreshStore {
+    ranges: BTreeMap<(Id, Id), usize>, // (start, end) -> copies, removing one leaves the others
+    ingredients: BTreeMap<Id, usize>,  // id -> copies, kept sorted for the queries
+    fresh: IntervalSet<Id>,            // union of ranges
+}
+
+impl FreshStore {
+    pub fn from_puzzle(input: &str) -> FreshStore {
+        let (ranges, ingredients) = parse(input);
+        let mut store = FreshStore {
+            fresh: fresh_set(&ranges),
+            ..FreshStore::default()
+        };
+        let changes = ranges
+            .into_iter()
+            .map(Change::AddRange)
+            .chain(ingredients.into_iter().map(Change::AddIngredient));
+        changes.for_each(|change| {
+            store.record(&change);
+        });
+        store
+    }
+
+    pub fn load(path: &Path) -> Result<FreshStore, String> {
+        let log = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        FreshStore::from_log(&log)
+    }
+
+    pub fn from_log(log: &str) -> Result<FreshStore, String> {
+        let mut lines = log.lines();
+        let header = lines.next().unwrap_or_default();
+        match header.split_once(' ') {
+            Some((HEADER, version)) if version == VERSION.to_string() => {}
+            Some((HEADER, version)) => return Err(format!("unsupported version {version}")),
+            _ => return Err(format!("not a {HEADER} file")),
+        }
+        // replaying into `fresh` would shift its ranges on every change, so
+        // it is built once at the end
+        let mut store = FreshStore::default();
+        for (line, number) in lines.zip(2..) {
+            let change = Change::parse(line)
+                .ok_or_else(|| format!("line {number}: invalid change {line:?}"))?;
+            store.record(&change);
+        }
+        store.fresh = store
+            .ranges
+            .keys()
+            .map(|&(start, end)| start..=end)
+            .collect();
+        Ok(store)
+    }
+
+    // Compacted log holding only the current state
+    pub fn to_log(&self) -> String {
+        let ranges = self.ranges.iter().flat_map(|(&(start, end), &copies)| {
+            iter::repeat_n(Change::AddRange(FreshRange { start, end }), copies)
+        });
+        let ingredients = self
+            .ingredients
+            .iter()
+            .flat_map(|(&id, &copies)| iter::repeat_n(Change::AddIngredient(id), copies));
+        let mut log = format!("{HEADER} {VERSION}\n");
+        ranges
+            .chain(ingredients)
+            .for_each(|change| log += &(change.to_line() + "\n"));
+        log
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_log()).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    // Applies the changes and appends them to the log at path
+    pub fn update(&mut self, path: &Path, changes: &[Change]) -> Result<(), String> {
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        let lines: String = changes.iter().map(|c| c.to_line() + "\n").collect();
+        file.write_all(lines.as_bytes())
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        changes.iter().for_each(|change| self.apply(change));
+        Ok(())
+    }
+
+    pub fn apply(&mut self, change: &Change) {
+        let changed = self.record(change);
+        match change {
+            Change::AddRange(r) => self.fresh.insert(r.start..=r.end),
+            Change::RemoveRange(r) if changed => {
+                // put back what the remaining ranges overlapping r still cover
+                self.fresh.remove(r.start..=r.end);
+                self.ranges
+                    .range(..=(r.end, Id::MAX))
+                    .filter(|&(&(_, end), _)| end >= r.start)
+                    .for_each(|(&(start, end), _)| self.fresh.insert(start..=end));
+            }
+            _ => {}
+        }
+    }
+
+    // Updates ranges and ingredients but not `fresh`, false if nothing changed
+    fn record(&mut self, change: &Change) -> bool {
+        match change {
+            Change::AddRange(r) => add_one(&mut self.ranges, (r.start, r.end)),
+            Change::RemoveRange(r) => remove_one(&mut self.ranges, (r.start, r.end)),
+            Change::AddIngredient(id) => add_one(&mut self.ingredients, *id),
+            Change::RemoveIngredient(id) => remove_one(&mut self.ingredients, *id),
+        }
+    }
+
+    pub fn is_fresh(&self, id: Id) -> bool {
+        self.fresh.contains(id)
+    }
+
+    pub fn count_fresh_ingredients(&self) -> usize {
+        self.ingredients
+            .iter()
+            .filter(|&(&id, _)| self.fresh.contains(id))
+            .map(|(_, &copies)| copies)
+            .sum()
+    }
+
+    pub fn count_fresh_ids(&self) -> u128 {
+        self.fresh.total_len()
+    }
+}
+
+fn add_one<K: Ord>(copies: &mut BTreeMap<K, usize>, key: K) -> bool {
+    *copies.entry(key).or_default() += 1;
+    true
+}
+
+// Removes one copy of key, false if there was none
+fn remove_one<K: Ord>(copies: &mut BTreeMap<K, usize>, key: K) -> bool {
+    let Entry::Occupied(mut entry) = copies.entry(key) else {
+        return false;
+    };
+    if *entry.get() > 1 {
+        *entry.get_mut() -= 1;
+    } else {
+        entry.remove();
+    }
+    true
+}
+
+#[cfg(test)]
+fn temp_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("{name}-{}.db", std::process::id()))
+}
+
+#[test]
+fn test_from_puzzle() {
+    let store = FreshStore::from_puzzle(EXAMPLE);
+    assert_eq!(store.count_fresh_ingredients(), 3);
+    assert_eq!(store.count_fresh_ids(), 14);
+    assert!(store.is_fresh(17));
+    assert!(!store.is_fresh(8));
+}
+
+#[test]
+fn test_log_round_trip() {
+    let store = FreshStore::from_puzzle(EXAMPLE);
+    let log = store.to_log();
+    assert!(log.starts_with("fresh-db 1\n+range 3-5\n+range 10-14\n"));
+    assert!(log.ends_with("+ingredient 17\n+ingredient 32\n"));
+    let loaded = FreshStore::from_log(&log).unwrap();
+    assert_eq!(loaded.to_log(), log);
+    assert_eq!(loaded.count_fresh_ingredients(), 3);
+}
+
+#[test]
+fn test_from_log_errors() {
+    assert_eq!(
+        FreshStore::from_log("fresh-db 2\n").unwrap_err(),
+        "unsupported version 2"
+    );
+    assert_eq!(
+        FreshStore::from_log("3-5\n").unwrap_err(),
+        "not a fresh-db file"
+    );
+    assert_eq!(
+        FreshStore::from_log("fresh-db 1\n+range 3-5\n+range 7\n").unwrap_err(),
+        "line 3: invalid change \"+range 7\""
+    );
+}
+
+#[test]
+fn test_apply_changes() {
+    let mut store = FreshStore::from_puzzle(EXAMPLE);
+    store.apply(&Change::RemoveRange(FreshRange { start: 12, end: 18 }));
+    assert_eq!(store.count_fresh_ids(), 3 + 5 + 5);
+    assert!(!store.is_fresh(15));
+    store.apply(&Change::AddRange(FreshRange { start: 6, end: 9 }));
+    assert!(store.is_fresh(8));
+    store.apply(&Change::RemoveIngredient(17));
+    store.apply(&Change::AddIngredient(7));
+    assert_eq!(store.count_fresh_ingredients(), 4);
+    // removing a range that is not stored changes nothing
+    store.apply(&Change::RemoveRange(FreshRange { start: 1, end: 2 }));
+    assert_eq!(store.count_fresh_ids(), 3 + 4 + 5 + 5);
+}
+
+#[test]
+fn test_remove_duplicate_range() {
+    let mut store = FreshStore::default();
+    let range = FreshRange { start: 1, end: 10 };
+    store.apply(&Change::AddRange(range.clone()));
+    store.apply(&Change::AddRange(range.clone()));
+    store.apply(&Change::RemoveRange(range.clone()));
+    assert!(store.is_fresh(5));
+    store.apply(&Change::RemoveRange(range));
+    assert!(!store.is_fresh(5));
+}
+
+#[test]
+fn test_apply_matches_rebuilt_union() {
+    let mut rng = Lcg::new(11);
+    let mut store = FreshStore::default();
+    let mut stored = Vec::new();
+    for _ in 0..500 {
+        let start = rng.below(100);
+        let range = FreshRange {
+            start,
+            end: start + rng.below(20),
+        };
+        if rng.below(3) > 0 || stored.is_empty() {
+            store.apply(&Change::AddRange(range.clone()));
+            stored.push(range);
+        } else {
+            let removed = stored.swap_remove(rng.below(stored.len() as u64) as usize);
+            store.apply(&Change::RemoveRange(removed));
+        }
+        assert_eq!(store.fresh, fresh_set(&stored));
+    }
+}
+
+#[test]
+fn test_from_log_replays_removals() {
+    let log = "fresh-db 1\n+range 1-10\n+range 5-20\n-range 1-10\n\
+               +ingredient 7\n+ingredient 3\n+ingredient 7\n-ingredient 3\n";
+    let store = FreshStore::from_log(log).unwrap();
+    assert_eq!(store.count_fresh_ids(), 16);
+    assert_eq!(store.count_fresh_ingredients(), 2);
+    assert_eq!(
+        store.to_log(),
+        "fresh-db 1\n+range 5-20\n+ingredient 7\n+ingredient 7\n"
+    );
+}
+
+#[test]
+fn test_save_update_load() {
+    let path = temp_path("test_save_update_load");
+    let mut store = FreshStore::from_puzzle(EXAMPLE);
+    store.save(&path).unwrap();
+    let changes = [
+        Change::AddRange(FreshRange { start: 30, end: 40 }),
+        Change::RemoveIngredient(5),
+    ];
+    store.update(&path, &changes).unwrap();
+    let log = fs::read_to_string(&path).unwrap();
+    assert!(log.ends_with("+ingredient 32\n+range 30-40\n-ingredient 5\n"));
+    let loaded = FreshStore::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.count_fresh_ingredients(), 3);
+    assert_eq!(loaded.count_fresh_ids(), store.count_fresh_ids());
+    assert!(loaded.is_fresh(32));
+    assert!(FreshStore::load(&path).is_err());
+}
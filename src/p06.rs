@@ -1,120 +1,351 @@
-use std::collections::HashMap;
+use crate::big_uint::BigUint;
 use crate::helpers;
+use crate::helpers::Grid;
+#[cfg(test)]
+use crate::helpers::Lcg;
 
 pub fn solve(part2: bool) -> String {
     let input = std::fs::read_to_string("input_06.txt").expect("could not read file");
-    if part2 {
-        solve_2(&input).to_string()
+    let result = if part2 {
+        solve_2(&input)
     } else {
-        solve_1(&input).to_string()
-    }
+        solve_1(&input)
+    };
+    result
+        .unwrap_or_else(|e| panic!("input_06.txt: {e}"))
+        .to_string()
 }
 
-fn solve_1(input: &str) -> u128 {
-    let problems = parse_problems(&input);
-    problems.iter().map(compute).sum()
+fn solve_1(input: &str) -> Result<BigUint, String> {
+    let problems = parse_problems(&input)?;
+    total(&problems, true)
 }
 
-fn solve_2(input: &str) -> u128 {
-    let problems = parse_input_cephalopod(&input);
-    problems.iter().map(compute).sum()
+fn solve_2(input: &str) -> Result<BigUint, String> {
+    let problems = parse_input_cephalopod(&input)?;
+    total(&problems, true)
 }
 
-fn compute(problem: &Problem) -> u128 {
-    let op = problem.operator;
-    problem.numbers[1..]
+// Sum of all results, problems overflowing u128 are redone with BigUint
+// if `wide` is set and their operator supports it
+fn total(problems: &[Problem], wide: bool) -> Result<BigUint, String> {
+    problems
         .iter()
-        .fold(problem.numbers[0], |acc, &i| op(acc, i))
+        .enumerate()
+        .map(|(column, problem)| match compute(problem) {
+            Ok(result) => Ok(BigUint::from(result)),
+            Err(ArithError::Overflow) if wide => compute_wide(problem).ok_or_else(|| {
+                format!(
+                    "problem {column} overflows, no wide {}",
+                    problem.operator.symbol
+                )
+            }),
+            Err(ArithError::Overflow) => Err(format!("problem {column} overflows")),
+            Err(ArithError::DivisionByZero) => Err(format!("problem {column} divides by zero")),
+        })
+        .sum()
+}
+
+fn compute(problem: &Problem) -> Result<u128, ArithError> {
+    let op = problem.operator.apply;
+    let numbers = &problem.numbers;
+    match problem.operator.assoc {
+        Assoc::Left => numbers[1..]
+            .iter()
+            .try_fold(numbers[0], |acc, &i| op(acc, i)),
+        Assoc::Right => {
+            let (&last, rest) = numbers.split_last().unwrap();
+            rest.iter().try_rfold(last, |acc, &i| op(i, acc))
+        }
+    }
+}
+
+fn compute_wide(problem: &Problem) -> Option<BigUint> {
+    // wide operators are associative, so the fold direction does not matter
+    let op = problem.operator.wide?;
+    let mut numbers = problem.numbers.iter().map(|&n| BigUint::from(n));
+    let first = numbers.next()?;
+    Some(numbers.fold(first, op))
 }
 
 struct Problem {
     numbers: Vec<u128>,
-    operator: fn(u128, u128) -> u128,
+    operator: &'static Operator,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArithError {
+    Overflow, // also below zero
+    DivisionByZero,
 }
 
-type BinOp = fn(u128, u128) -> u128;
+type BinOp = fn(u128, u128) -> Result<u128, ArithError>;
+type WideOp = fn(BigUint, BigUint) -> BigUint;
 
-fn add(x: u128, y: u128) -> u128 {
-    x + y
+// Grouping of a chain like a ^ b ^ c, which also fixes the fold direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Assoc {
+    Left,  // ((a op b) op c), folded from the first number
+    Right, // (a op (b op c)), folded from the last number
 }
 
-fn mul(x: u128, y: u128) -> u128 {
-    x * y
+#[derive(Debug)]
+struct Operator {
+    symbol: &'static str,
+    apply: BinOp,
+    assoc: Assoc,
+    wide: Option<WideOp>, // only where u128 operands can overflow and BigUint helps
 }
 
-fn parse_problems(input: &&str) -> Vec<Problem> {
-    let n_problems = input.lines().next().unwrap().split_whitespace().count();
+fn checked(result: Option<u128>) -> Result<u128, ArithError> {
+    result.ok_or(ArithError::Overflow)
+}
 
-    let mut iterators_per_line: Vec<_> = input
-        .lines()
-        .into_iter()
-        .map(|line| line.split_whitespace())
-        .collect();
+fn nonzero(divisor: u128) -> Result<u128, ArithError> {
+    if divisor == 0 {
+        Err(ArithError::DivisionByZero)
+    } else {
+        Ok(divisor)
+    }
+}
 
-    let columns: Vec<Vec<&str>> = (0..n_problems)
-        .map(|_| {
-            iterators_per_line
-                .iter_mut()
-                .map(|line| line.next().unwrap())
-                .collect()
-        })
+static OPERATORS: [Operator; 8] = [
+    Operator {
+        symbol: "+",
+        apply: |x, y| checked(x.checked_add(y)),
+        assoc: Assoc::Left,
+        wide: Some(|x, y| x + y),
+    },
+    Operator {
+        symbol: "*",
+        apply: |x, y| checked(x.checked_mul(y)),
+        assoc: Assoc::Left,
+        wide: Some(|x, y| x * y),
+    },
+    Operator {
+        symbol: "-",
+        apply: |x, y| checked(x.checked_sub(y)),
+        assoc: Assoc::Left,
+        wide: None,
+    },
+    Operator {
+        symbol: "/",
+        apply: |x, y| Ok(x / nonzero(y)?),
+        assoc: Assoc::Left,
+        wide: None,
+    },
+    Operator {
+        symbol: "%",
+        apply: |x, y| Ok(x % nonzero(y)?),
+        assoc: Assoc::Left,
+        wide: None,
+    },
+    Operator {
+        symbol: "^",
+        apply: |x, y| checked(u32::try_from(y).ok().and_then(|y| x.checked_pow(y))),
+        assoc: Assoc::Right,
+        wide: None,
+    },
+    Operator {
+        symbol: "min",
+        apply: |x, y| Ok(x.min(y)),
+        assoc: Assoc::Left,
+        wide: None,
+    },
+    Operator {
+        symbol: "max",
+        apply: |x, y| Ok(x.max(y)),
+        assoc: Assoc::Left,
+        wide: None,
+    },
+];
+
+fn operator(symbol: &str, column: usize) -> Result<&'static Operator, String> {
+    OPERATORS
+        .iter()
+        .find(|op| op.symbol == symbol)
+        .ok_or_else(|| format!("unknown operator {symbol:?} in problem {column}"))
+}
+
+fn parse_problems(input: &&str) -> Result<Vec<Problem>, String> {
+    let rows: Vec<Vec<&str>> = input
+        .lines()
+        .map(|line| line.split_whitespace().collect())
         .collect();
+    let columns = helpers::transpose(rows).ok_or("rows differ in number of entries")?;
 
-    let mut operators: HashMap<&str, BinOp> = HashMap::new();
-    operators.insert("+", add);
-    operators.insert("*", mul);
     columns
         .iter()
-        .map(|strings| Problem {
-            numbers: strings[0..(strings.len() - 1)]
+        .enumerate()
+        .map(|(column, strings)| {
+            let numbers = strings[0..(strings.len() - 1)]
                 .iter()
-                .map(|str| str.parse().unwrap())
-                .collect(),
-            operator: *operators.get(strings.last().unwrap()).unwrap(),
+                .enumerate()
+                .map(|(y, str)| {
+                    str.parse()
+                        .map_err(|_| format!("problem {column} has no number in row {y}: {str:?}"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if numbers.is_empty() {
+                return Err(format!("problem {column} has no numbers"));
+            }
+            Ok(Problem {
+                numbers,
+                operator: operator(strings.last().unwrap(), column)?,
+            })
         })
         .collect()
 }
 
-fn parse_input_cephalopod(input: &str) -> Vec<Problem> {
-    let mut operators: HashMap<&str, BinOp> = HashMap::new();
-    operators.insert("+", add);
-    operators.insert("*", mul);
-    let operators: Vec<BinOp> = input
-        .lines()
-        .last()
-        .unwrap()
-        .split_whitespace()
-        .map(|c| *operators.get(c).unwrap())
-        .collect();
-
-    let n_number_rows = input.lines().count() - 1;
-    let chars = input
-        .lines()
-        .map(|line| line.chars().collect::<Vec<_>>())
-        .collect::<Vec<_>>();
-
-    let number_chars = chars[..n_number_rows].to_vec();
+fn parse_input_cephalopod(input: &str) -> Result<Vec<Problem>, String> {
+    // padding restores trailing whitespace that editors like to strip
+    let sheet = Grid::parse(input, |c| c);
+    let Some(operator_row) = sheet.rows().last() else {
+        return Ok(Vec::new());
+    };
+    let columns = sheet.transposed();
+    let column_digits = |x: usize| &columns.row(x)[..sheet.height - 1];
 
-    let columns = helpers::transpose(number_chars);
+    // each problem starts at its operator and ends before the blank
+    // separator column in front of the next operator
+    let starts: Vec<usize> = (0..sheet.width)
+        .filter(|&x| operator_row[x] != ' ' && (x == 0 || operator_row[x - 1] == ' '))
+        .collect();
+    let ends = starts
+        .iter()
+        .skip(1)
+        .map(|&next| next - 1)
+        .chain([sheet.width]);
+    let blank = |x: usize| column_digits(x).iter().all(|&c| c == ' ');
 
-    let blocks = columns
-        .split(|col| col.iter().all(|&char| char == ' '))
-        .to_owned();
+    let mut errors = Vec::new();
+    if let Some(x) = (0..starts.first().copied().unwrap_or(sheet.width)).find(|&x| !blank(x)) {
+        errors.push(format!("digits in column {x} before the first operator"));
+    }
+    let mut problems = Vec::new();
+    for (column, (&start, end)) in starts.iter().zip(ends).enumerate() {
+        let symbol: String = operator_row[start..end].iter().collect();
+        let operator = match operator(symbol.trim_end(), column) {
+            Ok(operator) => operator,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        if end < sheet.width && !blank(end) {
+            errors.push(format!(
+                "problem {column} has digits in separator column {end}"
+            ));
+            continue;
+        }
+        // the digit columns form one block, blank columns after it only pad
+        // a wide symbol, digits after a gap have lost their operator
+        let digit_columns: Vec<usize> = (start..end).filter(|&x| !blank(x)).collect();
+        if let Some(gap) = digit_columns.windows(2).find(|w| w[1] > w[0] + 1) {
+            errors.push(format!("digits in column {} without an operator", gap[1]));
+            continue;
+        }
+        let numbers: Result<Vec<u128>, String> = digit_columns
+            .into_iter()
+            .rev()
+            .map(|x| {
+                let digits: String = column_digits(x).iter().collect();
+                digits.trim().parse().map_err(|_| {
+                    format!("problem {column} has no number in column {x}: {digits:?}")
+                })
+            })
+            .collect();
+        match numbers {
+            Ok(numbers) if numbers.is_empty() => {
+                errors.push(format!("problem {column} has no numbers"))
+            }
+            Ok(numbers) => problems.push(Problem { numbers, operator }),
+            Err(e) => errors.push(e),
+        }
+    }
+    if errors.is_empty() {
+        Ok(problems)
+    } else {
+        Err(errors.join("; "))
+    }
+}
 
-    let numbers_s: Vec<Vec<u128>> = blocks
-        .map(|cols| {
-            cols.iter()
-                .rev()
-                .map(|c| c.iter().collect::<String>().trim().parse().unwrap())
-                .collect()
+// Row-wise layout read by `parse_problems`, numbers right-aligned
+#[cfg(test)]
+fn render_problems(problems: &[Problem]) -> Result<String, String> {
+    let height = problems.first().map_or(0, |p| p.numbers.len());
+    if let Some(column) = problems.iter().position(|p| p.numbers.len() != height) {
+        return Err(format!(
+            "problem {column} has {} numbers, not {height}",
+            problems[column].numbers.len()
+        ));
+    }
+    let widths: Vec<usize> = problems
+        .iter()
+        .map(|p| {
+            let digits = p.numbers.iter().map(|n| n.to_string().len());
+            digits.chain([p.operator.symbol.len()]).max().unwrap()
         })
         .collect();
+    let row = |cells: Vec<String>| cells.join(" ") + "\n";
+    let number_rows = (0..height).map(|y| {
+        row(problems
+            .iter()
+            .zip(&widths)
+            .map(|(p, w)| format!("{:>w$}", p.numbers[y]))
+            .collect())
+    });
+    let operator_row = row(problems
+        .iter()
+        .zip(&widths)
+        .map(|(p, w)| format!("{:<w$}", p.operator.symbol))
+        .collect());
+    Ok(number_rows.chain([operator_row]).collect())
+}
 
-    numbers_s
-        .into_iter()
-        .zip(operators)
-        .map(|(numbers, operator)| Problem { numbers, operator })
-        .collect()
+// Column layout read by `parse_input_cephalopod`: every number is a column
+// of digits, top-aligned, the first number rightmost in its problem
+#[cfg(test)]
+fn render_cephalopod(problems: &[Problem]) -> String {
+    let height = problems
+        .iter()
+        .flat_map(|p| &p.numbers)
+        .map(|n| n.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let mut rows = vec![String::new(); height + 1];
+    for (column, problem) in problems.iter().enumerate() {
+        if column > 0 {
+            rows.iter_mut().for_each(|row| row.push(' '));
+        }
+        let width = problem.numbers.len().max(problem.operator.symbol.len());
+        let mut digit_columns: Vec<Vec<char>> = problem
+            .numbers
+            .iter()
+            .rev()
+            .map(|n| n.to_string().chars().collect())
+            .collect();
+        digit_columns.resize(width, Vec::new());
+        for (y, row) in rows[..height].iter_mut().enumerate() {
+            row.extend(
+                digit_columns
+                    .iter()
+                    .map(|digits| digits.get(y).copied().unwrap_or(' ')),
+            );
+        }
+        rows[height] += &format!("{:<width$}", problem.operator.symbol);
+    }
+    rows.into_iter().map(|row| row + "\n").collect()
+}
+
+#[cfg(test)]
+fn cephalopod_to_rows(input: &str) -> Result<String, String> {
+    render_problems(&parse_input_cephalopod(input)?)
+}
+
+#[cfg(test)]
+fn rows_to_cephalopod(input: &str) -> Result<String, String> {
+    Ok(render_cephalopod(&parse_problems(&input)?))
 }
 
 #[cfg(test)]
@@ -123,32 +354,256 @@ static EXAMPLE: &str = "123 328  51 64 \n 45 64  387 23 \n  6 98  215 314\n*   +
 
 #[test]
 fn test_parse_problems() {
-    let problems = parse_problems(&EXAMPLE);
+    let problems = parse_problems(&EXAMPLE).unwrap();
     assert_eq!(problems.len(), 4);
     assert_eq!(problems[0].numbers, vec![123, 45, 6]);
-    // Todo test operator equality?
+    assert_eq!(problems[0].operator.symbol, "*");
+    assert_eq!(problems[1].operator.symbol, "+");
 }
 
 #[test]
 fn test_parse_cephalopod() {
-    let problems = parse_input_cephalopod(&EXAMPLE);
+    let problems = parse_input_cephalopod(&EXAMPLE).unwrap();
     assert_eq!(problems.len(), 4);
     assert_eq!(problems[0].numbers, vec![356, 24, 1]);
     assert_eq!(problems[3].numbers, vec![4, 431, 623]);
-    assert_eq!(compute(&problems[0]), 8544);
-    assert_eq!(compute(&problems[3]), 1058);
+    assert_eq!(compute(&problems[0]), Ok(8544));
+    assert_eq!(compute(&problems[3]), Ok(1058));
+}
+
+#[test]
+fn test_parse_problems_not_a_number() {
+    let err = solve_1("1 x\n2 3\n+ *\n").err().unwrap();
+    assert_eq!(err, "problem 1 has no number in row 0: \"x\"");
+}
+
+#[test]
+fn test_parse_problems_without_numbers() {
+    let err = solve_1("+ *\n").err().unwrap();
+    assert_eq!(err, "problem 0 has no numbers");
+}
+
+#[test]
+fn test_parse_cephalopod_stripped_whitespace() {
+    let stripped: String = EXAMPLE
+        .lines()
+        .map(|l| l.trim_end().to_string() + "\n")
+        .collect();
+    let problems = parse_input_cephalopod(&stripped).unwrap();
+    assert_eq!(problems.len(), 4);
+    assert_eq!(problems[3].numbers, vec![4, 431, 623]);
+    assert_eq!(solve_2(&stripped).unwrap(), 3263827);
+    let ragged = "12\n3\n+\n";
+    let problems = parse_input_cephalopod(ragged).unwrap();
+    assert_eq!(problems[0].numbers, vec![2, 13]);
+}
+
+#[test]
+fn test_parse_cephalopod_multi_char_operators() {
+    let problems = parse_input_cephalopod("12   5\n 3  70\nmax +\n").unwrap();
+    assert_eq!(problems.len(), 2);
+    assert_eq!(problems[0].numbers, vec![23, 1]);
+    assert_eq!(problems[0].operator.symbol, "max");
+    assert_eq!(problems[1].numbers, vec![50, 7]);
+}
+
+#[test]
+fn test_parse_cephalopod_misaligned() {
+    let err = parse_input_cephalopod("123 45\n 45 67\n+  *  \n")
+        .err()
+        .unwrap();
+    assert_eq!(err, "problem 0 has digits in separator column 2");
+    let err = parse_input_cephalopod(" 12\n 34\n  +\n").err().unwrap();
+    assert_eq!(err, "digits in column 1 before the first operator");
+    let err = parse_input_cephalopod("1  4\n2x 5\n+  *\n").err().unwrap();
+    assert_eq!(err, "problem 0 has no number in column 1: \" x\"");
+    let err = parse_input_cephalopod("1    \n2    \n+  & *\n")
+        .err()
+        .unwrap();
+    assert_eq!(
+        err,
+        "unknown operator \"&\" in problem 1; problem 2 has no numbers"
+    );
+    assert!(parse_input_cephalopod("").unwrap().is_empty());
+    assert!(parse_input_cephalopod("\n\n").is_ok());
+}
+
+#[cfg(test)]
+fn summary(problems: &[Problem]) -> Vec<(&[u128], &str)> {
+    problems
+        .iter()
+        .map(|p| (p.numbers.as_slice(), p.operator.symbol))
+        .collect()
+}
+
+#[test]
+fn test_render_problems() {
+    let problems = parse_problems(&EXAMPLE).unwrap();
+    let rendered = render_problems(&problems).unwrap();
+    assert_eq!(
+        rendered,
+        "123 328  51  64\n 45  64 387  23\n  6  98 215 314\n*   +   *   +  \n"
+    );
+    let reparsed = parse_problems(&rendered.as_str()).unwrap();
+    assert_eq!(summary(&reparsed), summary(&problems));
+    let uneven = parse_input_cephalopod("12 3\n   4\n+  *\n").unwrap();
+    assert_eq!(
+        render_problems(&uneven).unwrap_err(),
+        "problem 1 has 1 numbers, not 2"
+    );
+}
+
+#[test]
+fn test_render_cephalopod() {
+    let problems = parse_input_cephalopod(EXAMPLE).unwrap();
+    let rendered = render_cephalopod(&problems);
+    // same problems as EXAMPLE, with every digit column top-aligned
+    assert_eq!(
+        rendered,
+        "123 328 351 644\n 45 64  287 23 \n  6 98   15 31 \n*   +   *   +  \n"
+    );
+    let reparsed = parse_input_cephalopod(&rendered).unwrap();
+    assert_eq!(summary(&reparsed), summary(&problems));
+}
+
+#[test]
+fn test_convert_layouts() {
+    let rows = cephalopod_to_rows(EXAMPLE).unwrap();
+    assert_eq!(
+        rows,
+        "356   8 175   4\n 24 248 581 431\n  1 369  32 623\n*   +   *   +  \n"
+    );
+    assert_eq!(solve_1(&rows).unwrap(), 3263827);
+    let cephalopod = rows_to_cephalopod(EXAMPLE).unwrap();
+    assert_eq!(solve_2(&cephalopod).unwrap(), 4277556);
+    let round_trip = cephalopod_to_rows(&cephalopod).unwrap();
+    let problems = parse_problems(&EXAMPLE).unwrap();
+    assert_eq!(round_trip, render_problems(&problems).unwrap());
+}
+
+#[test]
+fn test_render_round_trip() {
+    let mut rng = Lcg::new(3);
+    let mut next = |n| rng.below(n);
+    for _ in 0..50 {
+        let height = 1 + next(5) as usize;
+        let problems: Vec<Problem> = (0..1 + next(6))
+            .map(|_| Problem {
+                numbers: (0..height)
+                    .map(|_| {
+                        let digits = 1 + next(6) as u32;
+                        next(10u64.pow(digits)) as u128
+                    })
+                    .collect(),
+                operator: &OPERATORS[next(OPERATORS.len() as u64) as usize],
+            })
+            .collect();
+        let rows = render_problems(&problems).unwrap();
+        assert_eq!(
+            summary(&parse_problems(&rows.as_str()).unwrap()),
+            summary(&problems)
+        );
+        let cephalopod = render_cephalopod(&problems);
+        let reparsed = parse_input_cephalopod(&cephalopod).unwrap();
+        assert_eq!(summary(&reparsed), summary(&problems));
+    }
+}
+
+#[test]
+fn test_parse_cephalopod_missing_operator() {
+    let err = parse_input_cephalopod("12 34\n56 78\n+    \n")
+        .err()
+        .unwrap();
+    assert_eq!(err, "digits in column 3 without an operator");
+    let (head, tail) = EXAMPLE.rsplit_once('+').unwrap();
+    let without_last = format!("{head} {tail}");
+    let err = parse_input_cephalopod(&without_last).err().unwrap();
+    assert_eq!(err, "digits in column 12 without an operator");
+    // padding under a wide symbol is not a gap
+    let problems = parse_input_cephalopod("1  \n2  \nmax\n").unwrap();
+    assert_eq!(problems[0].numbers, vec![12]);
+}
+
+#[test]
+fn test_parse_problems_ragged() {
+    let err = parse_problems(&"1 2\n3\n+ *\n").err().unwrap();
+    assert_eq!(err, "rows differ in number of entries");
 }
 
 #[test]
 fn test_compute() {
-    let problems = parse_problems(&EXAMPLE);
-    assert_eq!(compute(&problems[0]), 33210);
-    assert_eq!(compute(&problems[1]), 490);
+    let problems = parse_problems(&EXAMPLE).unwrap();
+    assert_eq!(compute(&problems[0]), Ok(33210));
+    assert_eq!(compute(&problems[1]), Ok(490));
+}
+
+#[test]
+fn test_operators() {
+    let worksheet = "20 7 3 2 9  \n5  2 4 3 12 \n3  2 5 2 5  \n-  / % ^ min\n";
+    let problems = parse_problems(&worksheet).unwrap();
+    let results: Vec<u128> = problems.iter().map(|p| compute(p).unwrap()).collect();
+    // 20-5-3, 7/2/2, 3%4%5, 2^(3^2), min
+    assert_eq!(results, vec![12, 1, 3, 512, 5]);
+    let problems = parse_problems(&"1 4\n7 2\nmax max\n").unwrap();
+    assert_eq!(total(&problems, false), Ok(BigUint::from(7 + 4)));
+}
+
+#[test]
+fn test_unknown_operator() {
+    let err = parse_problems(&"1 2\n3 4\n+ &\n").err().unwrap();
+    assert_eq!(err, "unknown operator \"&\" in problem 1");
+    let err = parse_input_cephalopod("1 2\n3 4\n? +\n").err().unwrap();
+    assert_eq!(err, "unknown operator \"?\" in problem 0");
+}
+
+#[test]
+fn test_checked_arithmetic() {
+    let big = u128::MAX / 2;
+    let worksheet = format!("{big} 1 5 3\n{big} 2 0 4\n9 3 1 5\n+ - / -\n");
+    let problems = parse_problems(&worksheet.as_str()).unwrap();
+    assert_eq!(compute(&problems[0]), Err(ArithError::Overflow));
+    assert_eq!(compute(&problems[1]), Err(ArithError::Overflow));
+    assert_eq!(compute(&problems[2]), Err(ArithError::DivisionByZero));
+    assert_eq!(
+        total(&problems[..1], false),
+        Err("problem 0 overflows".to_string())
+    );
+    assert_eq!(
+        total(&problems, true),
+        Err("problem 1 overflows, no wide -".to_string())
+    );
+    assert_eq!(
+        total(&problems[2..], true),
+        Err("problem 0 divides by zero".to_string())
+    );
+    assert_eq!(
+        total(&problems[..1], true).unwrap().to_string(),
+        "340282366920938463463374607431768211463"
+    );
+}
+
+#[test]
+fn test_wide_fallback() {
+    let column = [u128::MAX; 3];
+    let worksheet = column
+        .iter()
+        .map(|n| format!("{n} 2\n"))
+        .collect::<String>()
+        + "* *\n";
+    let problems = parse_problems(&worksheet.as_str()).unwrap();
+    assert_eq!(compute(&problems[0]), Err(ArithError::Overflow));
+    let cube = BigUint::from(u128::MAX) * BigUint::from(u128::MAX) * BigUint::from(u128::MAX);
+    assert_eq!(total(&problems, true), Ok(cube + BigUint::from(8)));
+    let power = parse_problems(&"2\n200\n^\n").unwrap();
+    assert_eq!(
+        total(&power, true),
+        Err("problem 0 overflows, no wide ^".to_string())
+    );
 }
 
 #[test]
 fn test_solve_1_example() {
-    assert_eq!(solve_1(EXAMPLE), 4277556);
+    assert_eq!(solve_1(EXAMPLE).unwrap(), 4277556);
 }
 
 #[test]
@@ -158,7 +613,7 @@ fn test_solve_1() {
 
 #[test]
 fn test_solve_2_example() {
-    assert_eq!(solve_2(EXAMPLE), 3263827);
+    assert_eq!(solve_2(EXAMPLE).unwrap(), 3263827);
 }
 
 #[test]
@@ -1,22 +1,172 @@
+use crate::helpers::{Grid, Pos};
 use std::collections::{HashMap, HashSet};
+#[cfg(test)]
 use std::ops::{Add, AddAssign, Sub};
 
 pub fn solve(part2: bool) -> String {
     let input = std::fs::read_to_string("input_07.txt").expect("could not read file");
     if part2 {
-        solve_2(&input).to_string()
+        solve_2(&input)
+            .unwrap_or_else(|e| panic!("input_07.txt: {e}"))
+            .to_string()
     } else {
         solve_1(&input).to_string()
     }
 }
 
 fn solve_1(input: &str) -> usize {
+    count_splits(&parse_optics(input))
+}
+
+fn solve_2(input: &str) -> Result<u128, String> {
+    count_timelines(&parse_optics(input))
+}
+
+type Dir = (isize, isize); // (dy, dx)
+const DOWN: Dir = (1, 0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Beam {
+    pos: Pos, // cell the beam leaves
+    dir: Dir,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Element {
+    Empty,
+    Splitter(usize), // sends beams 1..=reach cells to either side, fan-out 2 * reach
+    Mirror(char),    // '/' or '\'
+    Absorber,
+}
+
+struct Optics {
+    start: Pos,
+    elements: Grid<Element>,
+}
+
+// '^' splits to the neighbors, a digit n splits up to n cells to each side
+fn parse_optics(input: &str) -> Optics {
+    let elements = Grid::parse(input, |c| match c {
+        '^' => Element::Splitter(1),
+        '1'..='9' => Element::Splitter(c.to_digit(10).unwrap() as usize),
+        '/' | '\\' => Element::Mirror(c),
+        '#' => Element::Absorber,
+        _ => Element::Empty,
+    });
+    let start = (0, input.lines().next().unwrap().find('S').unwrap());
+    Optics { start, elements }
+}
+
+impl Optics {
+    fn start_beam(&self) -> Beam {
+        Beam {
+            pos: self.start,
+            dir: DOWN,
+        }
+    }
+
+    // Beams leaving the next cell, None for each one that ends there or
+    // leaves the manifold
+    fn advance(&self, beam: Beam) -> Vec<Option<Beam>> {
+        let Some(cell) = self.elements.offset(beam.pos, beam.dir) else {
+            return vec![None];
+        };
+        let (dy, dx) = beam.dir;
+        match self.elements[cell] {
+            Element::Splitter(reach) => (1..=reach as isize)
+                .flat_map(|k| [-k, k])
+                .map(|k| {
+                    let side = self.elements.offset(cell, (k * dx, k * dy))?;
+                    self.enter(side, beam.dir)
+                })
+                .collect(),
+            _ => vec![self.enter(cell, beam.dir)],
+        }
+    }
+
+    // Beam moving in dir after the element at cell acted on it. Only beams
+    // hitting a splitter head-on split, a beam sent sideways onto a splitter
+    // passes it, as in the row-by-row manifold.
+    fn enter(&self, cell: Pos, dir: Dir) -> Option<Beam> {
+        let (dy, dx) = dir;
+        match self.elements[cell] {
+            Element::Empty | Element::Splitter(_) => Some(Beam { pos: cell, dir }),
+            Element::Absorber => None,
+            Element::Mirror(mirror) => {
+                let dir = if mirror == '/' { (-dx, -dy) } else { (dx, dy) };
+                Some(Beam { pos: cell, dir })
+            }
+        }
+    }
+
+    fn splitter_hit(&self, beam: Beam) -> Option<Pos> {
+        let cell = self.elements.offset(beam.pos, beam.dir)?;
+        matches!(self.elements[cell], Element::Splitter(_)).then_some(cell)
+    }
+}
+
+// Splitters hit by at least one beam, beams that meet merge
+fn count_splits(optics: &Optics) -> usize {
+    let mut seen = HashSet::from([optics.start_beam()]);
+    let mut beams = vec![optics.start_beam()];
+    let mut splitters = HashSet::new();
+    while let Some(beam) = beams.pop() {
+        splitters.extend(optics.splitter_hit(beam));
+        for next in optics.advance(beam).into_iter().flatten() {
+            if seen.insert(next) {
+                beams.push(next);
+            }
+        }
+    }
+    splitters.len()
+}
+
+// Every split forks the timeline, fails if a beam can loop forever
+fn count_timelines(optics: &Optics) -> Result<u128, String> {
+    // depth first with an explicit stack, beams on the current path detect loops
+    let mut timelines: HashMap<Beam, u128> = HashMap::new();
+    let mut on_path = HashSet::new();
+    let mut stack = vec![optics.start_beam()];
+    while let Some(&beam) = stack.last() {
+        if timelines.contains_key(&beam) {
+            stack.pop();
+            continue;
+        }
+        on_path.insert(beam);
+        let next = optics.advance(beam);
+        let pending: Vec<Beam> = next
+            .iter()
+            .flatten()
+            .filter(|b| !timelines.contains_key(b))
+            .copied()
+            .collect();
+        if let Some(looping) = pending.iter().find(|b| on_path.contains(b)) {
+            return Err(format!("beam loops through {:?}", looping.pos));
+        }
+        if pending.is_empty() {
+            let count = next
+                .iter()
+                .map(|b| b.map_or(1, |b| timelines[&b]))
+                .try_fold(0u128, u128::checked_add)
+                .ok_or_else(|| format!("timelines through {:?} overflow", beam.pos))?;
+            timelines.insert(beam, count);
+            on_path.remove(&beam);
+            stack.pop();
+        } else {
+            stack.extend(pending);
+        }
+    }
+    Ok(timelines[&optics.start_beam()])
+}
+
+#[cfg(test)]
+fn solve_1_by_rows(input: &str) -> usize {
     let manifold = parse_manifold(input);
 
     let start_beams = BeamRow::from([manifold.start]);
     manifold
         .splitters
-        .iter()
+        .rows()
         .scan(start_beams, |beams, splitters| {
             let splits; // Todo: can I mix this into the tuple-destructuring?
             (*beams, splits) = pass_row_count_splits(beams, splitters);
@@ -25,54 +175,59 @@ fn solve_1(input: &str) -> usize {
         .sum()
 }
 
-fn solve_2(input: &str) -> usize {
+#[cfg(test)]
+fn solve_2_by_rows(input: &str) -> usize {
     let manifold = parse_manifold(input);
     let start_beams = QuantumBeamRow::from([(manifold.start, 1)]);
 
-    let mut line = 0;
     manifold
         .splitters
-        .iter()
+        .rows()
         .fold(start_beams, |beams, splitters| {
-            // let disp: String = (0..144).map(|i| if beams.contains_key(&i) {'|'} else {'.'}).collect();
-            // println!("{:?} - {:?}", line, disp);
-            line += 1;
             pass_row_quantum(&beams, splitters)
         })
         .values()
         .sum()
 }
 
+#[cfg(test)]
 type BeamRow = HashSet<usize>;
+#[cfg(test)]
 type QuantumBeamRow = HashMap<usize, usize>;
-type SplitterRow = HashSet<usize>;
+#[cfg(test)]
+type SplitterRow = [bool]; // true where there is a splitter
+#[cfg(test)]
 struct Manifold {
     start: usize,
-    splitters: Vec<SplitterRow>,
+    splitters: Grid<bool>,
 }
 
+#[cfg(test)]
 fn parse_manifold(input: &str) -> Manifold {
     let start = input.lines().nth(0).unwrap().find('S').unwrap();
-    let splitters = input
-        .lines()
-        .map(|line| line.match_indices('^').map(|(i, _)| i).collect())
-        .collect();
+    let splitters = Grid::parse(input, |c| c == '^');
     Manifold { start, splitters }
 }
 
+#[cfg(test)]
+fn has_splitter(splitters: &SplitterRow, beam: usize) -> bool {
+    splitters.get(beam) == Some(&true)
+}
+
 #[cfg(test)]
 fn pass_row(beams: &BeamRow, splitters: &SplitterRow) -> BeamRow {
     let (new_beams, _) = pass_row_count_splits(beams, splitters);
     new_beams
 }
 
+#[cfg(test)]
 fn pass_row_count_splits(beams: &BeamRow, splitters: &SplitterRow) -> (BeamRow, usize) {
     let mut splits = 0;
     let new_beams = beams
         .iter()
         .cloned()
         .flat_map(|beam| {
-            if splitters.contains(&beam) {
+            if has_splitter(splitters, beam) {
                 splits += 1;
                 vec![beam.sub(1), beam.add(1)]
             } else {
@@ -83,13 +238,14 @@ fn pass_row_count_splits(beams: &BeamRow, splitters: &SplitterRow) -> (BeamRow,
     (new_beams, splits)
 }
 
+#[cfg(test)]
 fn pass_row_quantum(beams: &QuantumBeamRow, splitters: &SplitterRow) -> QuantumBeamRow {
     let mut new_beams = QuantumBeamRow::new();
 
     beams
         .iter()
         .flat_map(|(&beam, count)| {
-            if splitters.contains(&beam) {
+            if has_splitter(splitters, beam) {
                 vec![(beam - 1, count), (beam + 1, count)]
             } else {
                 vec![(beam, count)]
@@ -121,20 +277,21 @@ static EXAMPLE: &str = ".......S.......
 #[test]
 fn test_parse_manifold() {
     let manifold = parse_manifold(EXAMPLE);
+    let splitters_in_row = |y| -> Vec<usize> {
+        let row = manifold.splitters.row(y);
+        (0..row.len()).filter(|&x| has_splitter(row, x)).collect()
+    };
     assert_eq!(manifold.start, 7);
-    assert_eq!(manifold.splitters[2], SplitterRow::from([7]));
-    assert_eq!(
-        manifold.splitters[14],
-        SplitterRow::from([1, 3, 5, 7, 9, 13])
-    );
+    assert_eq!(splitters_in_row(2), vec![7]);
+    assert_eq!(splitters_in_row(14), vec![1, 3, 5, 7, 9, 13]);
 }
 
 #[test]
 fn test_pass_row() {
     let manifold = parse_manifold(EXAMPLE);
     let start_beam = BeamRow::from([manifold.start]);
-    let beams_after_first_splitter = pass_row(&start_beam, &manifold.splitters[2]);
-    let beams_after_second = pass_row(&beams_after_first_splitter, &manifold.splitters[4]);
+    let beams_after_first_splitter = pass_row(&start_beam, manifold.splitters.row(2));
+    let beams_after_second = pass_row(&beams_after_first_splitter, manifold.splitters.row(4));
     assert_eq!(beams_after_first_splitter, BeamRow::from([6, 8]));
     assert_eq!(beams_after_second, BeamRow::from([5, 7, 9]));
 }
@@ -143,8 +300,8 @@ fn test_pass_row() {
 fn test_pass_row_count_splits() {
     let manifold = parse_manifold(EXAMPLE);
     let beam = BeamRow::from([manifold.start]);
-    let (beam, splits_first) = pass_row_count_splits(&beam, &manifold.splitters[2]);
-    let (_, splits_second) = pass_row_count_splits(&beam, &manifold.splitters[4]);
+    let (beam, splits_first) = pass_row_count_splits(&beam, manifold.splitters.row(2));
+    let (_, splits_second) = pass_row_count_splits(&beam, manifold.splitters.row(4));
     assert_eq!(splits_first, 1);
     assert_eq!(splits_second, 2);
 }
@@ -153,12 +310,133 @@ fn test_pass_row_count_splits() {
 fn test_pass_row_quantum() {
     let manifold = parse_manifold(EXAMPLE);
     let beams = QuantumBeamRow::from([(manifold.start, 1)]);
-    let beams = pass_row_quantum(&beams, &manifold.splitters[2]);
+    let beams = pass_row_quantum(&beams, manifold.splitters.row(2));
     assert_eq!(beams, QuantumBeamRow::from([(6, 1), (8, 1)]));
-    let beams = pass_row_quantum(&beams, &manifold.splitters[4]);
+    let beams = pass_row_quantum(&beams, manifold.splitters.row(4));
     assert_eq!(beams, QuantumBeamRow::from([(5, 1), (7, 2), (9, 1)]));
 }
 
+#[test]
+fn test_engine_matches_rows() {
+    assert_eq!(solve_1(EXAMPLE), solve_1_by_rows(EXAMPLE));
+    assert_eq!(solve_2(EXAMPLE), Ok(solve_2_by_rows(EXAMPLE) as u128));
+}
+
+#[test]
+fn test_mirrors() {
+    // down, right at '\\', down at '\\', left at '/', down at '/'
+    let optics = parse_optics("..S..\n.....\n..\\.\\\n.....\n./../\n.....\n");
+    let mut beam = optics.start_beam();
+    let mut path = vec![beam.pos];
+    while let [Some(next)] = optics.advance(beam)[..] {
+        beam = next;
+        path.push(beam.pos);
+    }
+    assert_eq!(
+        path,
+        vec![
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+            (4, 4),
+            (4, 3),
+            (4, 2),
+            (4, 1),
+            (5, 1)
+        ]
+    );
+    assert_eq!(count_timelines(&optics), Ok(1));
+}
+
+#[test]
+fn test_absorbers() {
+    let optics = parse_optics("..S..\n.....\n..^..\n.#...\n.....\n");
+    assert_eq!(count_splits(&optics), 1);
+    assert_eq!(count_timelines(&optics), Ok(2));
+    let blocked = parse_optics("..S..\n..#..\n..^..\n");
+    assert_eq!(count_splits(&blocked), 0);
+    assert_eq!(count_timelines(&blocked), Ok(1));
+}
+
+#[test]
+fn test_splitter_fan_out() {
+    let optics = parse_optics("...S...\n.......\n...2...\n.......\n.^.....\n.......\n");
+    let start = optics.start_beam();
+    let below_splitter = Beam {
+        pos: (1, 3),
+        ..start
+    };
+    let sides: Vec<Pos> = optics
+        .advance(below_splitter)
+        .into_iter()
+        .map(|b| b.unwrap().pos)
+        .collect();
+    assert_eq!(sides, vec![(2, 2), (2, 4), (2, 1), (2, 5)]);
+    assert_eq!(count_splits(&optics), 2);
+    assert_eq!(count_timelines(&optics), Ok(4 + 1));
+    // the outermost beams of a wide splitter leave the manifold
+    let edge = parse_optics("S..\n3..\n");
+    assert_eq!(count_timelines(&edge), Ok(6));
+}
+
+#[test]
+fn test_elements_beside_splitter() {
+    // the left beam is absorbed, the right one is turned away from the splitter
+    let optics = parse_optics(".S..\n....\n#^\\.\n....\n");
+    let below_splitter = Beam {
+        pos: (1, 1),
+        ..optics.start_beam()
+    };
+    let sides = optics.advance(below_splitter);
+    assert_eq!(
+        sides,
+        vec![
+            None,
+            Some(Beam {
+                pos: (2, 2),
+                dir: (0, 1)
+            })
+        ]
+    );
+    assert_eq!(count_timelines(&optics), Ok(2));
+    // a beam sent sideways onto a splitter passes it
+    let adjacent = "..S..\n.....\n.^^..\n.....\n";
+    assert_eq!(solve_1(adjacent), solve_1_by_rows(adjacent));
+    assert_eq!(solve_2(adjacent), Ok(solve_2_by_rows(adjacent) as u128));
+}
+
+#[test]
+fn test_timelines_overflow() {
+    // the timelines grow exponentially with the rows of splitters
+    let optics = parse_optics(&("..S..\n".to_string() + &"^^^^^\n".repeat(300)));
+    assert_eq!(
+        count_timelines(&optics),
+        Err("timelines through (139, 3) overflow".to_string())
+    );
+}
+
+#[test]
+fn test_sideways_splitter() {
+    // a beam moving right splits up and down
+    let optics = parse_optics(".S...\n.....\n.\\.^.\n.....\n");
+    assert_eq!(count_splits(&optics), 1);
+    assert_eq!(count_timelines(&optics), Ok(2));
+}
+
+#[test]
+fn test_loop() {
+    // the right beam of the splitter enters a ring of mirrors
+    let looping = parse_optics("S/.\\\n^...\n.\\./\n");
+    assert_eq!(count_splits(&looping), 1);
+    assert_eq!(
+        count_timelines(&looping),
+        Err("beam loops through (1, 1)".to_string())
+    );
+}
+
 #[test]
 fn test_solve_1_example() {
     assert_eq!(solve_1(EXAMPLE), 21);
@@ -171,7 +449,7 @@ fn test_solve_1() {
 
 #[test]
 fn test_solve_2_example() {
-    assert_eq!(solve_2(EXAMPLE), 40);
+    assert_eq!(solve_2(EXAMPLE), Ok(40));
 }
 
 #[test]
@@ -1,3 +1,5 @@
+use crate::helpers::Grid;
+
 pub fn solve(part2: bool) -> String {
     let input = std::fs::read_to_string("input_12.txt").expect("could not read file");
     if part2 {
@@ -56,11 +58,7 @@ pub fn trivially_impossible(presents: &Vec<PresentShape>, regions: &Vec<Region>)
 fn cant_even_fit_tiles(region: &&Region, presents: &Vec<PresentShape>) -> bool {
     let present_sizes = presents
         .iter()
-        .map(|p| {
-            p.iter()
-                .map(|r| r.iter().filter(|&&p| p).count())
-                .sum::<usize>()
-        })
+        .map(|p| p.positions().filter(|&pos| p[pos]).count())
         .collect::<Vec<_>>();
 
     let region_tiles = region.width * region.height;
@@ -73,7 +71,7 @@ fn cant_even_fit_tiles(region: &&Region, presents: &Vec<PresentShape>) -> bool {
     present_tiles > region_tiles
 }
 
-pub(crate) type PresentShape = Vec<Vec<bool>>;
+pub(crate) type PresentShape = Grid<bool>;
 
 pub(crate) struct Region {
     pub(crate) width: usize,
@@ -91,14 +89,7 @@ pub(crate) fn parse(input: &str) -> (Vec<PresentShape>, Vec<Region>) {
 }
 
 fn parse_shape(input: &str) -> PresentShape {
-    input
-        .split_once(':')
-        .unwrap()
-        .1
-        .trim()
-        .lines()
-        .map(|line| line.chars().map(|c| c == '#').collect())
-        .collect()
+    Grid::parse(input.split_once(':').unwrap().1.trim(), |c| c == '#')
 }
 
 fn parse_region(input: &str) -> Region {
@@ -1,5 +1,7 @@
 use crate::p12_grid::{empty_region, put_shape_into, RegionMap};
 #[cfg(test)]
+use crate::helpers::Grid;
+#[cfg(test)]
 use crate::p12::EXAMPLE;
 use std::collections::{HashMap, HashSet};
 use std::iter;
@@ -207,7 +209,7 @@ fn test_collision_pair() {
 
 #[test]
 fn test_collision_pair_massive_block() {
-    let block = iter::repeat_n(iter::repeat_n(true, 3).collect(), 3).collect();
+    let block = Grid::new(3, 3, true);
     let compatibility = compatibility_pair(&block, &block);
     assert_eq!(compatibility.len(), 0);
 }
@@ -1,13 +1,13 @@
-use crate::{helpers, p12};
-use std::iter;
+use crate::helpers::Grid;
+use crate::p12;
 use crate::p12::PresentShape;
 #[cfg(test)]
 use crate::p12::EXAMPLE;
 
-pub(crate)  type RegionMap = Vec<Vec<bool>>;
+pub(crate)  type RegionMap = Grid<bool>;
 
 pub(crate)  fn empty_region(w: usize, h: usize) -> RegionMap {
-    iter::repeat_n(iter::repeat_n(false, w).collect(), h).collect()
+    Grid::new(w, h, false)
 }
 
 pub(crate)  fn put_shape_into(
@@ -18,31 +18,22 @@ pub(crate)  fn put_shape_into(
 ) -> Option<RegionMap> {
     // todo: less cloning, more refs/views
     let mut shape = shape.clone();
-    (0..rot90).for_each(|_| shape = helpers::rot90(shape.clone()));
+    (0..rot90).for_each(|_| shape = shape.rotated90());
 
-    let fits = !region_map[offset.0..]
-        .iter()
-        .zip(shape.iter())
-        .map(|(region_row, shape_row)| {
-            region_row[offset.1..]
-                .iter()
-                .zip(shape_row.iter())
-                .any(|(&r, &s)| r && s)
-        })
-        .any(|both| both);
+    // parts of the shape sticking out of the region are ignored
+    let covered = shape
+        .positions()
+        .filter(|&pos| shape[pos])
+        .map(|(y, x)| (y + offset.0, x + offset.1))
+        .filter(|&pos| region_map.get(pos).is_some())
+        .collect::<Vec<_>>();
+
+    let fits = !covered.iter().any(|&pos| region_map[pos]);
 
     if fits {
         // insert_piece
         let mut filled_map = region_map.clone();
-        filled_map[offset.0..]
-            .iter_mut()
-            .zip(shape.iter())
-            .for_each(|(region_row, shape_row)| {
-                region_row[offset.1..]
-                    .iter_mut()
-                    .zip(shape_row.iter())
-                    .for_each(|(r, &s)| *r |= s)
-            });
+        covered.into_iter().for_each(|pos| filled_map[pos] = true);
         Some(filled_map)
     } else {
         None
@@ -61,11 +52,11 @@ fn test_put_shape_into_empty() {
 fn test_put_shape_into_not_twice() {
     let (presents, _) = p12::parse(EXAMPLE);
     let empty_region = empty_region(4, 4);
-    assert_eq!(empty_region[0][0], false);
+    assert_eq!(empty_region[(0, 0)], false);
     let region_with_first = put_shape_into(&empty_region, &presents[4], (0, 0), 0);
     assert!(region_with_first.is_some());
     let region_with_first = region_with_first.unwrap();
-    assert_eq!(region_with_first[0][0], true);
+    assert_eq!(region_with_first[(0, 0)], true);
     let region_with_second = put_shape_into(&region_with_first, &presents[4], (0, 0), 0);
     assert!(region_with_second.is_none());
 }